@@ -0,0 +1,480 @@
+pub mod blip;
+pub mod dmc;
+pub mod envelope;
+pub mod filter;
+pub mod frame_counter;
+pub mod length_counter;
+pub mod linear_counter;
+pub mod mixer;
+pub mod noise;
+pub mod pulse;
+pub mod ring_buffer;
+pub mod sweep;
+pub mod triangle;
+
+use blip::BlipBuffer;
+use dmc::Dmc;
+use filter::OutputFilter;
+use frame_counter::{FrameClock, FrameCounter};
+use noise::Noise;
+use pulse::Pulse;
+use ring_buffer::SampleRingBuffer;
+use sweep::PulseChannel;
+use triangle::Triangle;
+
+pub const PULSE1_REGISTERS: u16 = 0x4000;
+pub const PULSE1_REGISTERS_END: u16 = 0x4003;
+pub const PULSE2_REGISTERS: u16 = 0x4004;
+pub const PULSE2_REGISTERS_END: u16 = 0x4007;
+pub const TRIANGLE_REGISTERS: u16 = 0x4008;
+pub const TRIANGLE_REGISTERS_END: u16 = 0x400B;
+pub const NOISE_REGISTERS: u16 = 0x400C;
+pub const NOISE_REGISTERS_END: u16 = 0x400F;
+pub const DMC_REGISTERS: u16 = 0x4010;
+pub const DMC_REGISTERS_END: u16 = 0x4013;
+pub const STATUS: u16 = 0x4015;
+pub const FRAME_COUNTER: u16 = 0x4017;
+
+// 出力サンプルレート (44.1kHz / 48kHz など)
+pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+const SAMPLE_BUFFER_CAPACITY: usize = 16_384;
+// 映像1フレーム (NTSC) 分のCPUサイクル。フロントエンドが閉じなくてもこの間隔でオーディオフレームを閉じる
+const AUDIO_FRAME_CYCLES: usize = 29_781;
+
+// 地域によってクロックや周期テーブルが異なる
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Region {
+    Ntsc,
+    Pal,
+}
+
+impl Region {
+    // CPUのクロック周波数 (Hz)
+    pub fn cpu_clock_rate(&self) -> f64 {
+        match self {
+            Region::Ntsc => 1_789_773.0,
+            Region::Pal => 1_662_607.0,
+        }
+    }
+}
+
+pub struct NesAPU {
+    pub pulse1: Pulse,
+    pub pulse2: Pulse,
+    pub triangle: Triangle,
+    pub noise: Noise,
+    pub dmc: Dmc,
+    pub frame_counter: FrameCounter,
+    // CPUサイクルの累計。偶数サイクルでAPUサイクルが進む
+    cycles: usize,
+    region: Region,
+    sample_rate: u32,
+    blip: BlipBuffer,
+    output_filter: OutputFilter,
+    // フロントエンドが取り出す出力サンプル
+    pub samples: SampleRingBuffer,
+    // 直前に合成した振幅と、今のオーディオフレームで経過したCPUサイクル
+    last_amplitude: f32,
+    frame_cycles: usize,
+}
+
+impl NesAPU {
+    pub fn new() -> Self {
+        NesAPU {
+            pulse1: Pulse::new(PulseChannel::Pulse1),
+            pulse2: Pulse::new(PulseChannel::Pulse2),
+            triangle: Triangle::new(),
+            noise: Noise::new(Region::Ntsc),
+            dmc: Dmc::new(Region::Ntsc),
+            frame_counter: FrameCounter::new(Region::Ntsc),
+            cycles: 0,
+            region: Region::Ntsc,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            blip: BlipBuffer::new(Region::Ntsc.cpu_clock_rate(), DEFAULT_SAMPLE_RATE),
+            output_filter: OutputFilter::new(DEFAULT_SAMPLE_RATE),
+            samples: SampleRingBuffer::new(SAMPLE_BUFFER_CAPACITY),
+            last_amplitude: 0.0,
+            frame_cycles: 0,
+        }
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.noise.region = region;
+        self.dmc.region = region;
+        self.frame_counter.region = region;
+        self.reset_audio_output();
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+        self.reset_audio_output();
+    }
+
+    // クロック周波数かサンプルレートが変わったらリサンプラとフィルタを作り直す
+    fn reset_audio_output(&mut self) {
+        self.blip = BlipBuffer::new(self.region.cpu_clock_rate(), self.sample_rate);
+        self.output_filter = OutputFilter::new(self.sample_rate);
+        self.samples.clear();
+        self.last_amplitude = 0.0;
+        self.frame_cycles = 0;
+    }
+
+    // CPUサイクル単位で進める。バスアクセスごとに呼ばれるので、リサンプラの処理はフレーム境界まで溜める
+    pub fn tick(&mut self, cycles: usize) {
+        for _ in 0..cycles {
+            self.step();
+        }
+        if self.frame_cycles >= AUDIO_FRAME_CYCLES {
+            self.end_audio_frame();
+        }
+    }
+
+    // 合成した振幅の変化をリサンプラに渡す
+    fn synthesize(&mut self) {
+        let amplitude = self.output();
+        if amplitude != self.last_amplitude {
+            self.blip
+                .add_delta(self.frame_cycles, amplitude - self.last_amplitude);
+            self.last_amplitude = amplitude;
+        }
+        self.frame_cycles += 1;
+    }
+
+    // 確定した出力サンプルにフィルタをかけてリングバッファへ送る。
+    // フロントエンドはVBlankごとに呼んで、それまでのサンプルを取り出せる
+    pub fn end_audio_frame(&mut self) {
+        let output_filter = &mut self.output_filter;
+        let samples = &mut self.samples;
+        self.blip.end_frame(self.frame_cycles, |sample| {
+            samples.push(output_filter.process(sample))
+        });
+        self.frame_cycles = 0;
+    }
+
+    // 全チャンネルをミックスした現在の振幅 (0.0-1.0)
+    pub fn output(&self) -> f32 {
+        mixer::mix(
+            self.pulse1.output(),
+            self.pulse2.output(),
+            self.triangle.output(),
+            self.noise.output(),
+            self.dmc.output(),
+        )
+    }
+
+    fn step(&mut self) {
+        match self.frame_counter.clock() {
+            Some(FrameClock::Quarter) => self.clock_quarter_frame(),
+            Some(FrameClock::Half) => {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+            }
+            None => {}
+        }
+
+        self.triangle.clock_timer();
+        self.noise.clock_timer();
+        self.dmc.clock_timer();
+        if self.cycles % 2 == 1 {
+            self.pulse1.clock_timer();
+            self.pulse2.clock_timer();
+        }
+        self.cycles += 1;
+        self.synthesize();
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.pulse1.clock_quarter_frame();
+        self.pulse2.clock_quarter_frame();
+        self.triangle.clock_quarter_frame();
+        self.noise.clock_quarter_frame();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.pulse1.clock_half_frame();
+        self.pulse2.clock_half_frame();
+        self.triangle.clock_half_frame();
+        self.noise.clock_half_frame();
+    }
+
+    pub fn write_register(&mut self, addr: u16, data: u8) {
+        match addr {
+            PULSE1_REGISTERS..=PULSE1_REGISTERS_END => self.pulse1.write_register(addr, data),
+            PULSE2_REGISTERS..=PULSE2_REGISTERS_END => self.pulse2.write_register(addr, data),
+            TRIANGLE_REGISTERS..=TRIANGLE_REGISTERS_END => self.triangle.write_register(addr, data),
+            NOISE_REGISTERS..=NOISE_REGISTERS_END => self.noise.write_register(addr, data),
+            DMC_REGISTERS..=DMC_REGISTERS_END => self.dmc.write_register(addr, data),
+            STATUS => self.write_to_status(data),
+            FRAME_COUNTER => self.frame_counter.write(data, self.cycles % 2 == 1),
+            _ => {}
+        }
+    }
+
+    // 読み込むとフレーム割り込みフラグがクリアされる
+    pub fn read_status(&mut self) -> u8 {
+        let status = self.peek_status();
+        self.frame_counter.interrupt = false;
+        status
+    }
+
+    pub fn peek_status(&self) -> u8 {
+        let mut status = 0;
+        if self.pulse1.length_counter.is_active() {
+            status |= 0b0000_0001;
+        }
+        if self.pulse2.length_counter.is_active() {
+            status |= 0b0000_0010;
+        }
+        if self.triangle.length_counter.is_active() {
+            status |= 0b0000_0100;
+        }
+        if self.noise.length_counter.is_active() {
+            status |= 0b0000_1000;
+        }
+        if self.dmc.is_active() {
+            status |= 0b0001_0000;
+        }
+        if self.frame_counter.interrupt {
+            status |= 0b0100_0000;
+        }
+        if self.dmc.interrupt {
+            status |= 0b1000_0000;
+        }
+        status
+    }
+
+    // ---D NT21: チャンネルの有効/無効
+    fn write_to_status(&mut self, data: u8) {
+        self.pulse1
+            .length_counter
+            .set_enabled(data & 0b0000_0001 != 0);
+        self.pulse2
+            .length_counter
+            .set_enabled(data & 0b0000_0010 != 0);
+        self.triangle
+            .length_counter
+            .set_enabled(data & 0b0000_0100 != 0);
+        self.noise
+            .length_counter
+            .set_enabled(data & 0b0000_1000 != 0);
+        self.dmc.set_enabled(data & 0b0001_0000 != 0);
+    }
+
+    // CPUのIRQラインにつながる (レベルトリガ)
+    pub fn irq_status(&self) -> bool {
+        self.frame_counter.interrupt || self.dmc.interrupt
+    }
+}
+
+impl Default for NesAPU {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_reports_length_counters() {
+        let mut apu = NesAPU::new();
+        apu.write_register(0x4015, 0b0000_0011);
+        apu.write_register(0x4003, 0b0000_1000);
+        assert_eq!(apu.read_status(), 0b0000_0001);
+        apu.write_register(0x4007, 0b0000_1000);
+        assert_eq!(apu.read_status(), 0b0000_0011);
+
+        apu.write_register(0x4015, 0b0000_0010);
+        assert_eq!(apu.read_status(), 0b0000_0010);
+    }
+
+    #[test]
+    fn test_status_reports_triangle_and_noise() {
+        let mut apu = NesAPU::new();
+        apu.write_register(0x4015, 0b0000_1100);
+        apu.write_register(0x400B, 0b0000_1000);
+        apu.write_register(0x400F, 0b0000_1000);
+        assert_eq!(apu.read_status(), 0b0000_1100);
+    }
+
+    #[test]
+    fn test_set_region() {
+        let mut apu = NesAPU::new();
+        apu.set_region(Region::Pal);
+        apu.write_register(0x400E, 0x02);
+        assert_eq!(apu.noise.timer_period, 14);
+    }
+
+    #[test]
+    fn test_triangle_timer_runs_at_cpu_rate() {
+        let mut apu = NesAPU::new();
+        apu.write_register(0x4015, 0b0000_0100);
+        apu.write_register(0x4008, 0b0111_1111);
+        apu.write_register(0x400A, 0x02);
+        apu.write_register(0x400B, 0b0000_1000);
+        apu.clock_quarter_frame();
+
+        apu.tick(1);
+        assert_eq!(apu.triangle.output(), 14);
+        apu.tick(3);
+        assert_eq!(apu.triangle.output(), 13);
+    }
+
+    #[test]
+    fn test_dmc_status_and_irq() {
+        let mut apu = NesAPU::new();
+        apu.write_register(0x4010, 0b1000_0000);
+        apu.write_register(0x4015, 0b0001_0000);
+        assert_eq!(apu.read_status(), 0b0001_0000);
+        assert!(!apu.irq_status());
+
+        apu.dmc.fill_sample_buffer(0);
+        assert_eq!(apu.read_status(), 0b1000_0000);
+        assert!(apu.irq_status());
+
+        // $4015への書き込みでDMCの割り込みはクリアされる
+        apu.write_register(0x4015, 0);
+        assert!(!apu.irq_status());
+    }
+
+    #[test]
+    fn test_frame_irq_cleared_by_status_read() {
+        let mut apu = NesAPU::new();
+        apu.tick(29829);
+        assert!(apu.irq_status());
+        assert_eq!(apu.peek_status(), 0b0100_0000);
+        assert_eq!(apu.read_status(), 0b0100_0000);
+        assert_eq!(apu.peek_status(), 0);
+        assert!(!apu.irq_status());
+    }
+
+    #[test]
+    fn test_frame_counter_clocks_channels() {
+        let mut apu = NesAPU::new();
+        apu.write_register(0x4015, 0b0000_0101);
+        apu.write_register(0x4000, 0b0000_0000);
+        apu.write_register(0x4003, 0b0000_1000);
+        apu.write_register(0x4008, 0b0000_0011);
+        apu.write_register(0x400B, 0b0000_1000);
+
+        // 最初のクォーターフレームで線形カウンタがリロードされる
+        apu.tick(7457);
+        assert_eq!(apu.triangle.linear_counter.counter, 3);
+        assert_eq!(apu.pulse1.length_counter.counter, 254);
+
+        apu.tick(14913 - 7457);
+        assert_eq!(apu.triangle.linear_counter.counter, 2);
+        assert_eq!(apu.pulse1.length_counter.counter, 253);
+        assert_eq!(apu.triangle.length_counter.counter, 253);
+    }
+
+    #[test]
+    fn test_five_step_write_clocks_immediately() {
+        let mut apu = NesAPU::new();
+        apu.write_register(0x4015, 0b0000_0001);
+        apu.write_register(0x4003, 0b0000_1000);
+        apu.write_register(0x4017, 0b1000_0000);
+        apu.tick(3);
+        assert_eq!(apu.pulse1.length_counter.counter, 253);
+
+        // 5ステップモードではフレーム割り込みは起きない
+        apu.tick(40000);
+        assert!(!apu.irq_status());
+    }
+
+    // 周期0x0FD (約440Hz) の矩形波を鳴らす
+    fn play_pulse(apu: &mut NesAPU) {
+        apu.write_register(0x4015, 0b0000_0001);
+        apu.write_register(0x4000, 0b1011_1111);
+        apu.write_register(0x4002, 0xFD);
+        apu.write_register(0x4003, 0b0000_1000);
+    }
+
+    #[test]
+    fn test_samples_at_output_rate() {
+        let mut apu = NesAPU::new();
+        apu.set_sample_rate(48_000);
+        play_pulse(&mut apu);
+        // 179_000サイクル = 4800.5サンプル
+        for _ in 0..1000 {
+            apu.tick(179);
+        }
+        apu.end_audio_frame();
+        assert_eq!(apu.samples.len(), 4_800);
+
+        let mut out = vec![0.0; apu.samples.len()];
+        apu.samples.drain_f32(&mut out);
+        let peak = out
+            .iter()
+            .fold(0.0_f32, |peak, sample| peak.max(sample.abs()));
+        assert!(peak > 0.05);
+        assert!(peak < 1.0);
+        assert!(apu.samples.is_empty());
+    }
+
+    #[test]
+    fn test_audio_frame_closed_at_frame_boundary() {
+        let mut apu = NesAPU::new();
+        play_pulse(&mut apu);
+        for _ in 0..AUDIO_FRAME_CYCLES - 1 {
+            apu.tick(1);
+        }
+        // フレーム境界まではリサンプラにサンプルを溜めておく
+        assert!(apu.samples.is_empty());
+
+        apu.tick(1);
+        // 29_781サイクル = 733.8サンプル
+        assert_eq!(apu.samples.len(), 733);
+    }
+
+    #[test]
+    fn test_dc_offset_is_filtered_out() {
+        // 停止中の三角波は15を出力し続けるが、ハイパスフィルタで無音になる
+        let mut apu = NesAPU::new();
+        apu.tick(1_000_000);
+        assert_eq!(apu.samples.len(), apu.samples.capacity());
+        let mut out = [1; 64];
+        assert_eq!(apu.samples.drain_i16(&mut out), 64);
+        assert!(out.iter().all(|&sample| sample == 0));
+    }
+
+    #[test]
+    fn test_set_region_changes_sample_timing() {
+        let mut apu = NesAPU::new();
+        apu.set_region(Region::Pal);
+        apu.tick(1_662_607 / 10);
+        apu.end_audio_frame();
+        assert!((4_409..=4_410).contains(&apu.samples.len()));
+    }
+
+    #[test]
+    fn test_length_load_ignored_while_disabled() {
+        let mut apu = NesAPU::new();
+        apu.write_register(0x4003, 0b0000_1000);
+        assert_eq!(apu.read_status(), 0);
+    }
+
+    #[test]
+    fn test_pulse_timer_runs_at_half_cpu_rate() {
+        let mut apu = NesAPU::new();
+        apu.write_register(0x4015, 0b0000_0001);
+        apu.write_register(0x4000, 0b0101_1111); // デューティ25%, 一定音量15
+        apu.write_register(0x4002, 0x08);
+        apu.write_register(0x4003, 0b0000_1000);
+        assert_eq!(apu.pulse1.output(), 0);
+
+        // 最初のAPUサイクルでシーケンサが1つ進む
+        apu.tick(1);
+        assert_eq!(apu.pulse1.output(), 0);
+        apu.tick(1);
+        assert_eq!(apu.pulse1.output(), 15);
+
+        // タイマー周期8: APU 9サイクル = CPU 18サイクルごとに1ステップ進む
+        apu.tick(35);
+        assert_eq!(apu.pulse1.output(), 15);
+        apu.tick(1);
+        assert_eq!(apu.pulse1.output(), 0);
+    }
+}
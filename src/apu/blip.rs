@@ -0,0 +1,153 @@
+use std::f64::consts::PI;
+
+// 1サンプルを何分割した位置まで振幅の変化を表現するか
+const PHASES: usize = 32;
+// 帯域制限したステップを何サンプルに広げるか
+const KERNEL_WIDTH: usize = 16;
+// 出力のナイキスト周波数に対するカットオフの比
+const CUTOFF: f64 = 0.9;
+
+lazy_static! {
+    static ref KERNEL: Vec<[f32; KERNEL_WIDTH]> = (0..PHASES).map(build_kernel).collect();
+}
+
+// 窓関数をかけたsinc関数。合計が1になるよう正規化する
+fn build_kernel(phase: usize) -> [f32; KERNEL_WIDTH] {
+    let half = (KERNEL_WIDTH / 2) as f64;
+    let frac = phase as f64 / PHASES as f64;
+
+    let mut taps = [0.0; KERNEL_WIDTH];
+    for (i, tap) in taps.iter_mut().enumerate() {
+        let x = i as f64 - (half - 1.0) - frac;
+        let sinc = if x == 0.0 {
+            1.0
+        } else {
+            (PI * CUTOFF * x).sin() / (PI * CUTOFF * x)
+        };
+        let blackman = 0.42 + 0.5 * (PI * x / half).cos() + 0.08 * (2.0 * PI * x / half).cos();
+        *tap = sinc * blackman;
+    }
+
+    let sum: f64 = taps.iter().sum();
+    taps.map(|tap| (tap / sum) as f32)
+}
+
+// 振幅の変化 (差分) を帯域制限したステップとして出力サンプル列に書き込み、
+// 読み出し時に積分することでエイリアシングのないリサンプリングを行う (blip_buf方式)
+pub struct BlipBuffer {
+    // 入力1クロックあたりの出力サンプル数
+    factor: f64,
+    // 現在のフレーム先頭に対応する出力サンプル位置 (小数部を含む)
+    offset: f64,
+    deltas: Vec<f32>,
+    integrator: f32,
+}
+
+impl BlipBuffer {
+    pub fn new(clock_rate: f64, sample_rate: u32) -> Self {
+        BlipBuffer {
+            factor: sample_rate as f64 / clock_rate,
+            offset: 0.0,
+            deltas: vec![],
+            integrator: 0.0,
+        }
+    }
+
+    // フレーム先頭から clock クロック目で振幅が delta だけ変化した
+    pub fn add_delta(&mut self, clock: usize, delta: f32) {
+        let position = self.offset + clock as f64 * self.factor;
+        let index = position as usize;
+        let phase = ((position - index as f64) * PHASES as f64) as usize;
+
+        if self.deltas.len() < index + KERNEL_WIDTH {
+            self.deltas.resize(index + KERNEL_WIDTH, 0.0);
+        }
+        for (i, tap) in KERNEL[phase].iter().enumerate() {
+            self.deltas[index + i] += delta * tap;
+        }
+    }
+
+    // clocks クロック分のフレームを終え、確定した出力サンプルを output に渡す
+    pub fn end_frame(&mut self, clocks: usize, mut output: impl FnMut(f32)) {
+        self.offset += clocks as f64 * self.factor;
+        let count = self.offset as usize;
+        if self.deltas.len() < count {
+            self.deltas.resize(count, 0.0);
+        }
+
+        for delta in self.deltas.drain(..count) {
+            self.integrator += delta;
+            output(self.integrator);
+        }
+        self.offset -= count as f64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLOCK_RATE: f64 = 1_789_773.0;
+
+    #[test]
+    fn test_kernel_is_normalized() {
+        for kernel in KERNEL.iter() {
+            let sum: f32 = kernel.iter().sum();
+            assert!((sum - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_sample_count() {
+        let mut blip = BlipBuffer::new(CLOCK_RATE, 44_100);
+        let mut count = 0;
+        // 1_789_770クロック = 44099.9サンプル
+        for _ in 0..10 {
+            blip.end_frame(178_977, |_| count += 1);
+        }
+        assert_eq!(count, 44_099);
+    }
+
+    #[test]
+    fn test_step_settles_to_delta() {
+        let mut blip = BlipBuffer::new(CLOCK_RATE, 48_000);
+        blip.add_delta(100, 0.5);
+        let mut samples = vec![];
+        blip.end_frame(10_000, |sample| samples.push(sample));
+
+        assert_eq!(samples[0], 0.0);
+        assert!((samples.last().unwrap() - 0.5).abs() < 1e-5);
+    }
+
+    fn step_response(clock: usize) -> Vec<f32> {
+        let mut blip = BlipBuffer::new(CLOCK_RATE, 44_100);
+        blip.add_delta(clock, 1.0);
+        let mut samples = vec![];
+        blip.end_frame(2_000, |sample| samples.push(sample));
+        samples
+    }
+
+    #[test]
+    fn test_step_keeps_sub_sample_position() {
+        // サンプル位置ちょうどの変化はほぼ1サンプルで立ち上がる
+        let aligned = step_response(0);
+        assert!(aligned[6] < 0.1);
+        assert!(aligned[7] > 0.9);
+
+        // サンプルの間 (位相0.49) で起きた変化は中間の値になる
+        let between = step_response(20);
+        assert!(between[6] < 0.0);
+        assert!((0.4..0.6).contains(&between[7]));
+        assert!(between[8] > 1.0);
+    }
+
+    #[test]
+    fn test_delta_spanning_frames() {
+        let mut blip = BlipBuffer::new(CLOCK_RATE, 44_100);
+        let mut samples = vec![];
+        blip.add_delta(50, 1.0);
+        blip.end_frame(60, |sample| samples.push(sample));
+        blip.end_frame(2_000, |sample| samples.push(sample));
+        assert!((samples.last().unwrap() - 1.0).abs() < 1e-5);
+    }
+}
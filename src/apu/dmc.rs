@@ -0,0 +1,294 @@
+use super::Region;
+
+// CPUサイクル単位のタイマー周期
+#[rustfmt::skip]
+const NTSC_RATE_TABLE: [u16; 16] = [
+    428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+];
+#[rustfmt::skip]
+const PAL_RATE_TABLE: [u16; 16] = [
+    398, 354, 316, 298, 276, 236, 210, 198, 176, 148, 132, 118, 98, 78, 66, 50,
+];
+
+const SAMPLE_ADDRESS_BASE: u16 = 0xC000;
+
+// デルタ変調チャンネル ($4010-$4013)
+// サンプルはバス経由でCPUのメモリから1バイトずつ読み込む (DMAはBus側で行う)
+#[derive(Debug, PartialEq)]
+pub struct Dmc {
+    pub region: Region,
+    pub irq_enabled: bool,
+    pub looping: bool,
+    pub timer_period: u16,
+    timer: u16,
+    // 7bitの出力レベル
+    pub output_level: u8,
+    pub sample_address: u16,
+    pub sample_length: u16,
+    current_address: u16,
+    pub bytes_remaining: u16,
+    sample_buffer: Option<u8>,
+    shift_register: u8,
+    bits_remaining: u8,
+    silence: bool,
+    pub interrupt: bool,
+}
+
+impl Dmc {
+    pub fn new(region: Region) -> Self {
+        Dmc {
+            region,
+            irq_enabled: false,
+            looping: false,
+            timer_period: rate_table(region)[0],
+            timer: 0,
+            output_level: 0,
+            sample_address: SAMPLE_ADDRESS_BASE,
+            sample_length: 1,
+            current_address: SAMPLE_ADDRESS_BASE,
+            bytes_remaining: 0,
+            sample_buffer: None,
+            shift_register: 0,
+            bits_remaining: 8,
+            silence: true,
+            interrupt: false,
+        }
+    }
+
+    pub fn write_register(&mut self, index: u16, data: u8) {
+        match index & 0b11 {
+            // IL-- RRRR
+            0 => {
+                self.irq_enabled = data & 0b1000_0000 != 0;
+                if !self.irq_enabled {
+                    self.interrupt = false;
+                }
+                self.looping = data & 0b0100_0000 != 0;
+                self.timer_period = rate_table(self.region)[(data & 0b1111) as usize];
+            }
+            // -DDD DDDD
+            1 => self.output_level = data & 0b0111_1111,
+            // $C000 + A * 64
+            2 => self.sample_address = SAMPLE_ADDRESS_BASE + ((data as u16) << 6),
+            // L * 16 + 1
+            3 => self.sample_length = ((data as u16) << 4) + 1,
+            _ => unreachable!(),
+        }
+    }
+
+    // $4015 のbit4。書き込むと割り込みフラグはクリアされる
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.interrupt = false;
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    fn restart(&mut self) {
+        self.current_address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+    }
+
+    // サンプルバッファが空で読むべきバイトが残っていれば、DMAで読むアドレスを返す
+    pub fn dma_request(&self) -> Option<u16> {
+        if self.sample_buffer.is_none() && self.bytes_remaining > 0 {
+            Some(self.current_address)
+        } else {
+            None
+        }
+    }
+
+    // DMAで読み込んだバイトを受け取る
+    pub fn fill_sample_buffer(&mut self, data: u8) {
+        self.sample_buffer = Some(data);
+        self.current_address = if self.current_address == 0xFFFF {
+            0x8000
+        } else {
+            self.current_address + 1
+        };
+
+        self.bytes_remaining -= 1;
+        if self.bytes_remaining == 0 {
+            if self.looping {
+                self.restart();
+            } else if self.irq_enabled {
+                self.interrupt = true;
+            }
+        }
+    }
+
+    // CPUサイクルごとに呼ばれる
+    pub fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.timer_period - 1;
+        self.clock_output();
+    }
+
+    // シフトレジスタの1ビットごとに出力レベルを±2する
+    fn clock_output(&mut self) {
+        if !self.silence {
+            if self.shift_register & 1 != 0 {
+                if self.output_level <= 125 {
+                    self.output_level += 2;
+                }
+            } else if self.output_level >= 2 {
+                self.output_level -= 2;
+            }
+        }
+        self.shift_register >>= 1;
+
+        self.bits_remaining -= 1;
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+            match self.sample_buffer.take() {
+                Some(data) => {
+                    self.silence = false;
+                    self.shift_register = data;
+                }
+                None => self.silence = true,
+            }
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    // 0-127 の出力レベル
+    pub fn output(&self) -> u8 {
+        self.output_level
+    }
+}
+
+fn rate_table(region: Region) -> &'static [u16; 16] {
+    match region {
+        Region::Ntsc => &NTSC_RATE_TABLE,
+        Region::Pal => &PAL_RATE_TABLE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 出力ユニットを1ビット分進める
+    fn clock_output_bit(dmc: &mut Dmc) {
+        for _ in 0..dmc.timer_period {
+            dmc.clock_timer();
+        }
+    }
+
+    #[test]
+    fn test_write_registers() {
+        let mut dmc = Dmc::new(Region::Ntsc);
+        dmc.write_register(0, 0b1100_1111);
+        dmc.write_register(1, 0xFF);
+        dmc.write_register(2, 0x01);
+        dmc.write_register(3, 0x02);
+        assert!(dmc.irq_enabled);
+        assert!(dmc.looping);
+        assert_eq!(dmc.timer_period, 54);
+        assert_eq!(dmc.output_level, 0x7F);
+        assert_eq!(dmc.sample_address, 0xC040);
+        assert_eq!(dmc.sample_length, 0x21);
+
+        let mut pal = Dmc::new(Region::Pal);
+        pal.write_register(0, 0b0000_1111);
+        assert_eq!(pal.timer_period, 50);
+    }
+
+    #[test]
+    fn test_dma_request() {
+        let mut dmc = Dmc::new(Region::Ntsc);
+        dmc.write_register(2, 0x01);
+        assert_eq!(dmc.dma_request(), None);
+
+        dmc.set_enabled(true);
+        assert_eq!(dmc.dma_request(), Some(0xC040));
+        dmc.fill_sample_buffer(0xFF);
+        assert_eq!(dmc.dma_request(), None);
+        assert!(!dmc.is_active());
+    }
+
+    #[test]
+    fn test_address_wraps_to_8000() {
+        let mut dmc = Dmc::new(Region::Ntsc);
+        dmc.write_register(2, 0xFF);
+        dmc.write_register(3, 0xFF);
+        dmc.set_enabled(true);
+        for _ in 0..0x40 {
+            dmc.sample_buffer = None;
+            let addr = dmc.dma_request().unwrap();
+            dmc.fill_sample_buffer(0);
+            if addr == 0xFFFF {
+                break;
+            }
+        }
+        dmc.sample_buffer = None;
+        assert_eq!(dmc.dma_request(), Some(0x8000));
+    }
+
+    #[test]
+    fn test_loop_restarts_sample() {
+        let mut dmc = Dmc::new(Region::Ntsc);
+        dmc.write_register(0, 0b1100_0000);
+        dmc.set_enabled(true);
+        dmc.fill_sample_buffer(0);
+        assert!(dmc.is_active());
+        assert!(!dmc.interrupt);
+        dmc.sample_buffer = None;
+        assert_eq!(dmc.dma_request(), Some(0xC000));
+    }
+
+    #[test]
+    fn test_irq_at_end_of_sample() {
+        let mut dmc = Dmc::new(Region::Ntsc);
+        dmc.write_register(0, 0b1000_0000);
+        dmc.set_enabled(true);
+        dmc.fill_sample_buffer(0);
+        assert!(dmc.interrupt);
+
+        // IRQを無効にするか$4015に書き込むとクリアされる
+        dmc.write_register(0, 0b0000_0000);
+        assert!(!dmc.interrupt);
+    }
+
+    #[test]
+    fn test_output_level_follows_bits() {
+        let mut dmc = Dmc::new(Region::Ntsc);
+        dmc.write_register(1, 0x40);
+        dmc.set_enabled(true);
+        dmc.fill_sample_buffer(0b0000_0101);
+
+        // 最初の8ビットは無音 (サンプルバッファがまだ取り込まれていない)
+        for _ in 0..8 {
+            clock_output_bit(&mut dmc);
+        }
+        assert_eq!(dmc.output(), 0x40);
+
+        clock_output_bit(&mut dmc);
+        assert_eq!(dmc.output(), 0x42);
+        clock_output_bit(&mut dmc);
+        assert_eq!(dmc.output(), 0x40);
+        clock_output_bit(&mut dmc);
+        assert_eq!(dmc.output(), 0x42);
+    }
+
+    #[test]
+    fn test_output_level_clamps() {
+        let mut dmc = Dmc::new(Region::Ntsc);
+        dmc.write_register(1, 0x7F);
+        dmc.set_enabled(true);
+        dmc.fill_sample_buffer(0xFF);
+        for _ in 0..9 {
+            clock_output_bit(&mut dmc);
+        }
+        assert_eq!(dmc.output(), 0x7F);
+    }
+}
@@ -0,0 +1,110 @@
+// 音量を一定の間隔で15から0へ下げていくユニット。クォーターフレームごとに動く
+#[derive(Debug, Default, PartialEq)]
+pub struct Envelope {
+    pub start: bool,
+    pub looping: bool,
+    pub constant_volume: bool,
+    // 一定音量の値、またはディバイダの周期
+    pub volume: u8,
+    divider: u8,
+    decay_level: u8,
+}
+
+impl Envelope {
+    pub fn new() -> Self {
+        Envelope {
+            start: false,
+            looping: false,
+            constant_volume: false,
+            volume: 0,
+            divider: 0,
+            decay_level: 0,
+        }
+    }
+
+    // --LC VVVV
+    pub fn write(&mut self, data: u8) {
+        self.looping = data & 0b0010_0000 != 0;
+        self.constant_volume = data & 0b0001_0000 != 0;
+        self.volume = data & 0b0000_1111;
+    }
+
+    pub fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay_level = 15;
+            self.divider = self.volume;
+            return;
+        }
+
+        if self.divider > 0 {
+            self.divider -= 1;
+            return;
+        }
+
+        self.divider = self.volume;
+        if self.decay_level > 0 {
+            self.decay_level -= 1;
+        } else if self.looping {
+            self.decay_level = 15;
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        if self.constant_volume {
+            self.volume
+        } else {
+            self.decay_level
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_volume() {
+        let mut envelope = Envelope::new();
+        envelope.write(0b0001_0111);
+        assert_eq!(envelope.output(), 7);
+    }
+
+    #[test]
+    fn test_decay() {
+        let mut envelope = Envelope::new();
+        envelope.write(0b0000_0001);
+        envelope.start = true;
+        envelope.clock();
+        assert_eq!(envelope.output(), 15);
+
+        // 周期1 (2回に1回) で減っていく
+        envelope.clock();
+        assert_eq!(envelope.output(), 15);
+        envelope.clock();
+        assert_eq!(envelope.output(), 14);
+    }
+
+    #[test]
+    fn test_decay_loop() {
+        let mut envelope = Envelope::new();
+        envelope.write(0b0010_0000);
+        envelope.start = true;
+        for _ in 0..16 {
+            envelope.clock();
+        }
+        assert_eq!(envelope.output(), 0);
+        envelope.clock();
+        assert_eq!(envelope.output(), 15);
+    }
+
+    #[test]
+    fn test_decay_stops_at_zero_without_loop() {
+        let mut envelope = Envelope::new();
+        envelope.start = true;
+        for _ in 0..20 {
+            envelope.clock();
+        }
+        assert_eq!(envelope.output(), 0);
+    }
+}
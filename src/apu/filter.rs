@@ -0,0 +1,127 @@
+use std::f32::consts::PI;
+
+// 出力段の1次フィルタ (RC回路相当)
+#[derive(Debug, PartialEq)]
+pub enum Filter {
+    HighPass {
+        alpha: f32,
+        prev_in: f32,
+        prev_out: f32,
+    },
+    LowPass {
+        alpha: f32,
+        prev_out: f32,
+    },
+}
+
+impl Filter {
+    pub fn high_pass(sample_rate: u32, cutoff: f32) -> Self {
+        let rc = 1.0 / (2.0 * PI * cutoff);
+        let dt = 1.0 / sample_rate as f32;
+        Filter::HighPass {
+            alpha: rc / (rc + dt),
+            prev_in: 0.0,
+            prev_out: 0.0,
+        }
+    }
+
+    pub fn low_pass(sample_rate: u32, cutoff: f32) -> Self {
+        let rc = 1.0 / (2.0 * PI * cutoff);
+        let dt = 1.0 / sample_rate as f32;
+        Filter::LowPass {
+            alpha: dt / (rc + dt),
+            prev_out: 0.0,
+        }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        match self {
+            Filter::HighPass {
+                alpha,
+                prev_in,
+                prev_out,
+            } => {
+                *prev_out = *alpha * (*prev_out + input - *prev_in);
+                *prev_in = input;
+                *prev_out
+            }
+            Filter::LowPass { alpha, prev_out } => {
+                *prev_out += *alpha * (input - *prev_out);
+                *prev_out
+            }
+        }
+    }
+}
+
+// NES本体の出力段: 90Hzと440Hzのハイパス、14kHzのローパス
+#[derive(Debug, PartialEq)]
+pub struct OutputFilter {
+    filters: [Filter; 3],
+}
+
+impl OutputFilter {
+    pub fn new(sample_rate: u32) -> Self {
+        OutputFilter {
+            filters: [
+                Filter::high_pass(sample_rate, 90.0),
+                Filter::high_pass(sample_rate, 440.0),
+                Filter::low_pass(sample_rate, 14_000.0),
+            ],
+        }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        self.filters
+            .iter_mut()
+            .fold(input, |sample, filter| filter.process(sample))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_high_pass_removes_dc() {
+        let mut filter = Filter::high_pass(44_100, 90.0);
+        let first = filter.process(1.0);
+        assert!(first > 0.9);
+        let mut last = first;
+        for _ in 0..44_100 {
+            last = filter.process(1.0);
+        }
+        assert!(last.abs() < 0.001);
+    }
+
+    #[test]
+    fn test_low_pass_settles_to_input() {
+        let mut filter = Filter::low_pass(44_100, 14_000.0);
+        let first = filter.process(1.0);
+        assert!(first < 1.0);
+        for _ in 0..100 {
+            filter.process(1.0);
+        }
+        assert!((filter.process(1.0) - 1.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_low_pass_attenuates_nyquist() {
+        let mut filter = Filter::low_pass(44_100, 14_000.0);
+        let mut peak: f32 = 0.0;
+        for i in 0..1000 {
+            let input = if i % 2 == 0 { 1.0 } else { -1.0 };
+            peak = peak.max(filter.process(input).abs());
+        }
+        assert!(peak < 0.7);
+    }
+
+    #[test]
+    fn test_output_filter_removes_dc() {
+        let mut filter = OutputFilter::new(48_000);
+        let mut last = 0.0;
+        for _ in 0..48_000 {
+            last = filter.process(0.5);
+        }
+        assert!(last.abs() < 0.001);
+    }
+}
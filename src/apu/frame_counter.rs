@@ -0,0 +1,240 @@
+use super::Region;
+
+// 各ステップのCPUサイクル。5ステップ目は5ステップモードでのみ使われる
+const NTSC_STEP_CYCLES: [usize; 5] = [7457, 14913, 22371, 29829, 37281];
+const PAL_STEP_CYCLES: [usize; 5] = [8313, 16627, 24939, 33253, 41565];
+
+// $4017 の書き込みから、シーケンサがリセットされるまでのCPUサイクル
+const RESET_DELAY_EVEN: u8 = 3;
+const RESET_DELAY_ODD: u8 = 4;
+
+// フレームシーケンサが各チャンネルに送るクロック
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameClock {
+    // エンベロープと線形カウンタ
+    Quarter,
+    // Quarterに加えて長さカウンタとスイープ
+    Half,
+}
+
+// フレームカウンタ ($4017)
+#[derive(Debug, PartialEq)]
+pub struct FrameCounter {
+    pub region: Region,
+    // false: 4ステップモード, true: 5ステップモード
+    pub five_step: bool,
+    pub irq_inhibit: bool,
+    pub interrupt: bool,
+    cycle: usize,
+    reset_delay: u8,
+}
+
+impl FrameCounter {
+    pub fn new(region: Region) -> Self {
+        FrameCounter {
+            region,
+            five_step: false,
+            irq_inhibit: false,
+            interrupt: false,
+            cycle: 0,
+            reset_delay: 0,
+        }
+    }
+
+    // MI-- ----
+    // odd_cycle: 書き込みがAPUサイクルの後半 (奇数CPUサイクル) で起きたかどうか
+    pub fn write(&mut self, data: u8, odd_cycle: bool) {
+        self.five_step = data & 0b1000_0000 != 0;
+        self.irq_inhibit = data & 0b0100_0000 != 0;
+        if self.irq_inhibit {
+            self.interrupt = false;
+        }
+        self.reset_delay = if odd_cycle {
+            RESET_DELAY_ODD
+        } else {
+            RESET_DELAY_EVEN
+        };
+    }
+
+    // CPUサイクルごとに呼ばれ、チャンネルへ送るクロックを返す
+    pub fn clock(&mut self) -> Option<FrameClock> {
+        // リセットを待つ間も、それまでのシーケンスはそのまま進む
+        let clock = self.step();
+        if self.reset_delay == 0 {
+            return clock;
+        }
+        self.reset_delay -= 1;
+        if self.reset_delay > 0 {
+            return clock;
+        }
+
+        self.cycle = 0;
+        // 5ステップモードに切り替えると即座にクォーター/ハーフフレームが来る
+        if self.five_step {
+            return Some(FrameClock::Half);
+        }
+        clock
+    }
+
+    fn step(&mut self) -> Option<FrameClock> {
+        self.cycle += 1;
+        let steps = step_cycles(self.region);
+        if self.five_step {
+            return match self.cycle {
+                c if c == steps[0] || c == steps[2] => Some(FrameClock::Quarter),
+                c if c == steps[1] => Some(FrameClock::Half),
+                c if c == steps[4] => Some(FrameClock::Half),
+                c if c == steps[4] + 1 => {
+                    self.cycle = 0;
+                    None
+                }
+                _ => None,
+            };
+        }
+
+        // 4ステップモードでは最後のステップの前後3サイクルにわたって割り込みフラグが立つ
+        if (steps[3] - 1..=steps[3] + 1).contains(&self.cycle) && !self.irq_inhibit {
+            self.interrupt = true;
+        }
+        match self.cycle {
+            c if c == steps[0] || c == steps[2] => Some(FrameClock::Quarter),
+            c if c == steps[1] || c == steps[3] => Some(FrameClock::Half),
+            c if c == steps[3] + 1 => {
+                self.cycle = 0;
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+fn step_cycles(region: Region) -> &'static [usize; 5] {
+    match region {
+        Region::Ntsc => &NTSC_STEP_CYCLES,
+        Region::Pal => &PAL_STEP_CYCLES,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 指定サイクル数だけ進めて、クロックが来たサイクルを集める
+    fn collect_clocks(frame_counter: &mut FrameCounter, cycles: usize) -> Vec<(usize, FrameClock)> {
+        (1..=cycles)
+            .filter_map(|cycle| frame_counter.clock().map(|clock| (cycle, clock)))
+            .collect()
+    }
+
+    #[test]
+    fn test_four_step_sequence() {
+        let mut frame_counter = FrameCounter::new(Region::Ntsc);
+        let clocks = collect_clocks(&mut frame_counter, 29830 + 7457);
+        assert_eq!(
+            clocks,
+            vec![
+                (7457, FrameClock::Quarter),
+                (14913, FrameClock::Half),
+                (22371, FrameClock::Quarter),
+                (29829, FrameClock::Half),
+                (29830 + 7457, FrameClock::Quarter),
+            ]
+        );
+        assert!(frame_counter.interrupt);
+    }
+
+    #[test]
+    fn test_four_step_irq_timing() {
+        let mut frame_counter = FrameCounter::new(Region::Ntsc);
+        collect_clocks(&mut frame_counter, 29827);
+        assert!(!frame_counter.interrupt);
+        frame_counter.clock();
+        assert!(frame_counter.interrupt);
+
+        // 最後のステップの間はフラグをクリアしても立ち直す
+        frame_counter.interrupt = false;
+        frame_counter.clock();
+        assert!(frame_counter.interrupt);
+    }
+
+    #[test]
+    fn test_irq_inhibit() {
+        let mut frame_counter = FrameCounter::new(Region::Ntsc);
+        collect_clocks(&mut frame_counter, 29830);
+        assert!(frame_counter.interrupt);
+
+        frame_counter.write(0b0100_0000, false);
+        assert!(!frame_counter.interrupt);
+        collect_clocks(&mut frame_counter, 29830 * 2);
+        assert!(!frame_counter.interrupt);
+    }
+
+    #[test]
+    fn test_five_step_sequence() {
+        let mut frame_counter = FrameCounter::new(Region::Ntsc);
+        frame_counter.write(0b1000_0000, false);
+        let clocks = collect_clocks(&mut frame_counter, 3 + 37282);
+        assert_eq!(
+            clocks,
+            vec![
+                (3, FrameClock::Half),
+                (3 + 7457, FrameClock::Quarter),
+                (3 + 14913, FrameClock::Half),
+                (3 + 22371, FrameClock::Quarter),
+                (3 + 37281, FrameClock::Half),
+            ]
+        );
+        assert!(!frame_counter.interrupt);
+    }
+
+    #[test]
+    fn test_reset_delay_on_odd_cycle() {
+        let mut frame_counter = FrameCounter::new(Region::Ntsc);
+        frame_counter.write(0b1000_0000, true);
+        let clocks = collect_clocks(&mut frame_counter, 4);
+        assert_eq!(clocks, vec![(4, FrameClock::Half)]);
+    }
+
+    #[test]
+    fn test_first_quarter_clock_after_write_on_even_cycle() {
+        let mut frame_counter = FrameCounter::new(Region::Ntsc);
+        collect_clocks(&mut frame_counter, 1000);
+        frame_counter.write(0, false);
+        let clocks = collect_clocks(&mut frame_counter, 3 + 7457);
+        assert_eq!(clocks, vec![(3 + 7457, FrameClock::Quarter)]);
+    }
+
+    #[test]
+    fn test_first_quarter_clock_after_write_on_odd_cycle() {
+        let mut frame_counter = FrameCounter::new(Region::Ntsc);
+        collect_clocks(&mut frame_counter, 1000);
+        frame_counter.write(0, true);
+        let clocks = collect_clocks(&mut frame_counter, 4 + 7457);
+        assert_eq!(clocks, vec![(4 + 7457, FrameClock::Quarter)]);
+    }
+
+    #[test]
+    fn test_sequence_keeps_running_during_reset_delay() {
+        let mut frame_counter = FrameCounter::new(Region::Ntsc);
+        collect_clocks(&mut frame_counter, 7454);
+        // リセットされるサイクルに元のシーケンスのステップが重なってもクロックは失われない
+        frame_counter.write(0, false);
+        assert_eq!(
+            collect_clocks(&mut frame_counter, 3),
+            vec![(3, FrameClock::Quarter)]
+        );
+        assert_eq!(
+            collect_clocks(&mut frame_counter, 7457),
+            vec![(7457, FrameClock::Quarter)]
+        );
+    }
+
+    #[test]
+    fn test_pal_sequence() {
+        let mut frame_counter = FrameCounter::new(Region::Pal);
+        let clocks = collect_clocks(&mut frame_counter, 33254);
+        assert_eq!(clocks[0], (8313, FrameClock::Quarter));
+        assert_eq!(clocks[3], (33253, FrameClock::Half));
+        assert!(frame_counter.interrupt);
+    }
+}
@@ -0,0 +1,91 @@
+#[rustfmt::skip]
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20,  2, 40,  4, 80,  6, 160,  8, 60, 10, 14, 12, 26, 14,
+    12,  16, 24, 18, 48, 20, 96, 22, 192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+// 一定時間で音を止めるためのカウンタ。ハーフフレームごとに減る
+#[derive(Debug, Default, PartialEq)]
+pub struct LengthCounter {
+    pub enabled: bool,
+    pub halt: bool,
+    pub counter: u8,
+}
+
+impl LengthCounter {
+    pub fn new() -> Self {
+        LengthCounter {
+            enabled: false,
+            halt: false,
+            counter: 0,
+        }
+    }
+
+    // $4015 で無効にするとカウンタは即座に0になる
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.counter = 0;
+        }
+    }
+
+    // レジスタの上位5ビットでテーブルから値を読み込む
+    pub fn load(&mut self, index: u8) {
+        if self.enabled {
+            self.counter = LENGTH_TABLE[(index & 0b1_1111) as usize];
+        }
+    }
+
+    pub fn clock(&mut self) {
+        if !self.halt && self.counter > 0 {
+            self.counter -= 1;
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.counter > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_only_when_enabled() {
+        let mut length = LengthCounter::new();
+        length.load(1);
+        assert_eq!(length.counter, 0);
+
+        length.set_enabled(true);
+        length.load(1);
+        assert_eq!(length.counter, 254);
+    }
+
+    #[test]
+    fn test_clock_and_halt() {
+        let mut length = LengthCounter::new();
+        length.set_enabled(true);
+        length.load(3);
+        length.clock();
+        assert_eq!(length.counter, 1);
+
+        length.halt = true;
+        length.clock();
+        assert_eq!(length.counter, 1);
+
+        length.halt = false;
+        length.clock();
+        length.clock();
+        assert!(!length.is_active());
+    }
+
+    #[test]
+    fn test_disable_clears_counter() {
+        let mut length = LengthCounter::new();
+        length.set_enabled(true);
+        length.load(0);
+        length.set_enabled(false);
+        assert_eq!(length.counter, 0);
+    }
+}
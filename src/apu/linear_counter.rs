@@ -0,0 +1,75 @@
+// 三角波専用の長さカウンタ。クォーターフレームごとに動く
+#[derive(Debug, Default, PartialEq)]
+pub struct LinearCounter {
+    // 長さカウンタの停止フラグも兼ねる
+    pub control: bool,
+    pub reload_value: u8,
+    pub reload: bool,
+    pub counter: u8,
+}
+
+impl LinearCounter {
+    pub fn new() -> Self {
+        LinearCounter {
+            control: false,
+            reload_value: 0,
+            reload: false,
+            counter: 0,
+        }
+    }
+
+    // CRRR RRRR
+    pub fn write(&mut self, data: u8) {
+        self.control = data & 0b1000_0000 != 0;
+        self.reload_value = data & 0b0111_1111;
+    }
+
+    pub fn clock(&mut self) {
+        if self.reload {
+            self.counter = self.reload_value;
+        } else if self.counter > 0 {
+            self.counter -= 1;
+        }
+
+        // コントロールフラグが立っている間はリロードし続ける
+        if !self.control {
+            self.reload = false;
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.counter > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reload_and_count_down() {
+        let mut linear = LinearCounter::new();
+        linear.write(0b0000_0010);
+        linear.reload = true;
+        linear.clock();
+        assert_eq!(linear.counter, 2);
+        assert!(!linear.reload);
+
+        linear.clock();
+        linear.clock();
+        assert!(!linear.is_active());
+        linear.clock();
+        assert_eq!(linear.counter, 0);
+    }
+
+    #[test]
+    fn test_control_keeps_reloading() {
+        let mut linear = LinearCounter::new();
+        linear.write(0b1000_0011);
+        linear.reload = true;
+        linear.clock();
+        linear.clock();
+        assert_eq!(linear.counter, 3);
+        assert!(linear.reload);
+    }
+}
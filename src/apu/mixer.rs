@@ -0,0 +1,58 @@
+// ハードウェアの非線形なミキシングをテーブルで近似する
+// pulse_out = 95.52 / (8128 / (pulse1 + pulse2) + 100)
+// tnd_out   = 163.67 / (24329 / (3 * triangle + 2 * noise + dmc) + 100)
+lazy_static! {
+    static ref PULSE_TABLE: Vec<f32> = (0..31)
+        .map(|n| if n == 0 {
+            0.0
+        } else {
+            95.52 / (8128.0 / n as f32 + 100.0)
+        })
+        .collect();
+    static ref TND_TABLE: Vec<f32> = (0..203)
+        .map(|n| if n == 0 {
+            0.0
+        } else {
+            163.67 / (24329.0 / n as f32 + 100.0)
+        })
+        .collect();
+}
+
+// 各チャンネルの出力レベルを 0.0-1.0 の振幅にまとめる
+pub fn mix(pulse1: u8, pulse2: u8, triangle: u8, noise: u8, dmc: u8) -> f32 {
+    let pulse = PULSE_TABLE[(pulse1 + pulse2) as usize];
+    let tnd = TND_TABLE[3 * triangle as usize + 2 * noise as usize + dmc as usize];
+    pulse + tnd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_silence() {
+        assert_eq!(mix(0, 0, 0, 0, 0), 0.0);
+    }
+
+    #[test]
+    fn test_full_scale() {
+        let max = mix(15, 15, 15, 15, 127);
+        assert!((max - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_pulse_is_non_linear() {
+        // 2チャンネル同時に鳴らしても2倍にはならない
+        let single = mix(15, 0, 0, 0, 0);
+        let both = mix(15, 15, 0, 0, 0);
+        assert!(both < single * 2.0);
+        assert!((single - 0.1494).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_tnd_weights() {
+        // 三角波1段はノイズ1.5段、DMC3段と同じ重み
+        assert_eq!(mix(0, 0, 2, 0, 0), mix(0, 0, 0, 3, 0));
+        assert_eq!(mix(0, 0, 1, 0, 0), mix(0, 0, 0, 0, 3));
+    }
+}
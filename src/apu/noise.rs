@@ -0,0 +1,158 @@
+use super::envelope::Envelope;
+use super::length_counter::LengthCounter;
+use super::Region;
+
+// CPUサイクル単位のタイマー周期
+#[rustfmt::skip]
+const NTSC_PERIOD_TABLE: [u16; 16] = [
+    4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
+];
+#[rustfmt::skip]
+const PAL_PERIOD_TABLE: [u16; 16] = [
+    4, 8, 14, 30, 60, 88, 118, 148, 188, 236, 354, 472, 708, 944, 1890, 3778,
+];
+
+// ノイズチャンネル ($400C-$400F)
+#[derive(Debug, PartialEq)]
+pub struct Noise {
+    pub envelope: Envelope,
+    pub length_counter: LengthCounter,
+    pub region: Region,
+    // true: 短周期モード (93ステップ)。bit6をフィードバックに使う
+    pub mode: bool,
+    // 15bitの線形帰還シフトレジスタ
+    shift_register: u16,
+    pub timer_period: u16,
+    timer: u16,
+}
+
+impl Noise {
+    pub fn new(region: Region) -> Self {
+        Noise {
+            envelope: Envelope::new(),
+            length_counter: LengthCounter::new(),
+            region,
+            mode: false,
+            shift_register: 1,
+            timer_period: period_table(region)[0],
+            timer: 0,
+        }
+    }
+
+    pub fn write_register(&mut self, index: u16, data: u8) {
+        match index & 0b11 {
+            // --LC VVVV
+            0 => {
+                self.length_counter.halt = data & 0b0010_0000 != 0;
+                self.envelope.write(data);
+            }
+            // 未使用 ($400D)
+            1 => {}
+            // M--- PPPP
+            2 => {
+                self.mode = data & 0b1000_0000 != 0;
+                self.timer_period = period_table(self.region)[(data & 0b1111) as usize];
+            }
+            // LLLL L---
+            3 => {
+                self.length_counter.load(data >> 3);
+                self.envelope.start = true;
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // CPUサイクルごとに呼ばれる。周期の分だけ数えたらLFSRを1つ進める
+    pub fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.timer_period - 1;
+        let tap = if self.mode { 6 } else { 1 };
+        let feedback = (self.shift_register ^ (self.shift_register >> tap)) & 1;
+        self.shift_register = (self.shift_register >> 1) | (feedback << 14);
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length_counter.clock();
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.length_counter.is_active() || self.shift_register & 1 != 0 {
+            return 0;
+        }
+        self.envelope.output()
+    }
+}
+
+fn period_table(region: Region) -> &'static [u16; 16] {
+    match region {
+        Region::Ntsc => &NTSC_PERIOD_TABLE,
+        Region::Pal => &PAL_PERIOD_TABLE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lfsr_cycle_length(mode: bool) -> usize {
+        let mut noise = Noise::new(Region::Ntsc);
+        noise.mode = mode;
+        let start = noise.shift_register;
+        let mut steps = 0;
+        loop {
+            for _ in 0..noise.timer_period {
+                noise.clock_timer();
+            }
+            steps += 1;
+            if noise.shift_register == start {
+                return steps;
+            }
+        }
+    }
+
+    #[test]
+    fn test_long_mode_period() {
+        assert_eq!(lfsr_cycle_length(false), 32767);
+    }
+
+    #[test]
+    fn test_short_mode_period() {
+        assert_eq!(lfsr_cycle_length(true), 93);
+    }
+
+    #[test]
+    fn test_period_table_by_region() {
+        let mut ntsc = Noise::new(Region::Ntsc);
+        let mut pal = Noise::new(Region::Pal);
+        ntsc.write_register(2, 0b1000_1111);
+        pal.write_register(2, 0b0000_1111);
+        assert_eq!(ntsc.timer_period, 4068);
+        assert!(ntsc.mode);
+        assert_eq!(pal.timer_period, 3778);
+        assert!(!pal.mode);
+    }
+
+    #[test]
+    fn test_output() {
+        let mut noise = Noise::new(Region::Ntsc);
+        noise.length_counter.set_enabled(true);
+        noise.write_register(0, 0b0001_1001);
+        noise.write_register(3, 0b0000_1000);
+
+        // 初期値1はbit0が立っているので無音
+        assert_eq!(noise.output(), 0);
+        noise.clock_timer();
+        assert_eq!(noise.output(), 9);
+
+        noise.length_counter.set_enabled(false);
+        assert_eq!(noise.output(), 0);
+    }
+}
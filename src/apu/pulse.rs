@@ -0,0 +1,175 @@
+use super::envelope::Envelope;
+use super::length_counter::LengthCounter;
+use super::sweep::{PulseChannel, Sweep};
+
+// デューティ比ごとの出力波形 (12.5%, 25%, 50%, 25%反転)
+const DUTY_TABLE: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+// 矩形波チャンネル ($4000-$4003 / $4004-$4007)
+#[derive(Debug, PartialEq)]
+pub struct Pulse {
+    pub envelope: Envelope,
+    pub sweep: Sweep,
+    pub length_counter: LengthCounter,
+    duty: u8,
+    sequence_step: u8,
+    // 11bitのタイマー周期
+    pub timer_period: u16,
+    timer: u16,
+}
+
+impl Pulse {
+    pub fn new(channel: PulseChannel) -> Self {
+        Pulse {
+            envelope: Envelope::new(),
+            sweep: Sweep::new(channel),
+            length_counter: LengthCounter::new(),
+            duty: 0,
+            sequence_step: 0,
+            timer_period: 0,
+            timer: 0,
+        }
+    }
+
+    // 下位2ビットで4つのレジスタを選ぶ
+    pub fn write_register(&mut self, index: u16, data: u8) {
+        match index & 0b11 {
+            // DDLC VVVV
+            0 => {
+                self.duty = data >> 6;
+                self.length_counter.halt = data & 0b0010_0000 != 0;
+                self.envelope.write(data);
+            }
+            // EPPP NSSS
+            1 => self.sweep.write(data),
+            // TTTT TTTT
+            2 => self.timer_period = (self.timer_period & 0x0700) | data as u16,
+            // LLLL LTTT: シーケンサとエンベロープも再スタートする
+            3 => {
+                self.timer_period = (self.timer_period & 0x00FF) | (((data & 0b111) as u16) << 8);
+                self.length_counter.load(data >> 3);
+                self.sequence_step = 0;
+                self.envelope.start = true;
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // APUサイクル (CPU 2サイクル) ごとに呼ばれる
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            self.sequence_step = (self.sequence_step + 1) % 8;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length_counter.clock();
+        self.timer_period = self.sweep.clock(self.timer_period);
+    }
+
+    // 0-15 の出力レベル
+    pub fn output(&self) -> u8 {
+        if !self.length_counter.is_active()
+            || self.sweep.is_muting(self.timer_period)
+            || DUTY_TABLE[self.duty as usize][self.sequence_step as usize] == 0
+        {
+            return 0;
+        }
+        self.envelope.output()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playing_pulse() -> Pulse {
+        let mut pulse = Pulse::new(PulseChannel::Pulse1);
+        pulse.length_counter.set_enabled(true);
+        // デューティ50%, 一定音量10
+        pulse.write_register(0, 0b1001_1010);
+        pulse.write_register(2, 0x10);
+        pulse.write_register(3, 0b0000_1000);
+        pulse
+    }
+
+    #[test]
+    fn test_write_registers() {
+        let pulse = playing_pulse();
+        assert_eq!(pulse.duty, 2);
+        assert_eq!(pulse.timer_period, 0x010);
+        assert_eq!(pulse.length_counter.counter, 254);
+        assert!(pulse.envelope.start);
+    }
+
+    #[test]
+    fn test_timer_high_bits() {
+        let mut pulse = playing_pulse();
+        pulse.write_register(3, 0b0000_0101);
+        assert_eq!(pulse.timer_period, 0x510);
+        pulse.write_register(2, 0xAB);
+        assert_eq!(pulse.timer_period, 0x5AB);
+    }
+
+    #[test]
+    fn test_duty_sequence() {
+        let mut pulse = playing_pulse();
+        let mut outputs = vec![];
+        for _ in 0..8 {
+            outputs.push(pulse.output());
+            // タイマー周期+1回でシーケンサが1つ進む
+            for _ in 0..=pulse.timer_period {
+                pulse.clock_timer();
+            }
+        }
+        assert_eq!(outputs, vec![0, 10, 10, 10, 10, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_silenced_by_length_counter() {
+        let mut pulse = playing_pulse();
+        pulse.clock_timer();
+        assert_eq!(pulse.output(), 10);
+        pulse.length_counter.set_enabled(false);
+        assert_eq!(pulse.output(), 0);
+    }
+
+    #[test]
+    fn test_silenced_by_sweep() {
+        let mut pulse = playing_pulse();
+        pulse.clock_timer();
+        pulse.write_register(2, 0x07);
+        assert_eq!(pulse.output(), 0);
+    }
+
+    #[test]
+    fn test_envelope_restarts_on_length_write() {
+        let mut pulse = playing_pulse();
+        pulse.write_register(0, 0b1000_0000);
+        pulse.clock_quarter_frame();
+        pulse.clock_timer();
+        assert_eq!(pulse.output(), 15);
+    }
+
+    #[test]
+    fn test_half_frame_clocks_length_and_sweep() {
+        let mut pulse = playing_pulse();
+        pulse.write_register(1, 0b1000_0001);
+        pulse.clock_half_frame();
+        pulse.clock_half_frame();
+        assert_eq!(pulse.length_counter.counter, 252);
+        assert_eq!(pulse.timer_period, 0x024);
+    }
+}
@@ -0,0 +1,133 @@
+// フロントエンドが取り出す出力サンプルのリングバッファ
+// 取り出しが追いつかずに満杯になった場合は古いサンプルから捨てる
+pub struct SampleRingBuffer {
+    data: Vec<f32>,
+    read: usize,
+    len: usize,
+}
+
+impl SampleRingBuffer {
+    pub fn new(capacity: usize) -> Self {
+        // 容量0では書き込み位置を求める剰余がゼロ除算になる
+        assert!(capacity > 0, "sample ring buffer capacity must be non-zero");
+        SampleRingBuffer {
+            data: vec![0.0; capacity],
+            read: 0,
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, sample: f32) {
+        if self.len == self.capacity() {
+            self.read = (self.read + 1) % self.capacity();
+            self.len -= 1;
+        }
+        let write = (self.read + self.len) % self.capacity();
+        self.data[write] = sample;
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<f32> {
+        if self.len == 0 {
+            return None;
+        }
+        let sample = self.data[self.read];
+        self.read = (self.read + 1) % self.capacity();
+        self.len -= 1;
+        Some(sample)
+    }
+
+    // 取り出したサンプル数を返す
+    pub fn drain_f32(&mut self, out: &mut [f32]) -> usize {
+        self.drain_with(out, |sample| sample)
+    }
+
+    pub fn drain_i16(&mut self, out: &mut [i16]) -> usize {
+        self.drain_with(out, |sample| {
+            (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+        })
+    }
+
+    fn drain_with<T>(&mut self, out: &mut [T], convert: impl Fn(f32) -> T) -> usize {
+        let count = out.len().min(self.len);
+        for slot in out.iter_mut().take(count) {
+            *slot = convert(self.pop().unwrap());
+        }
+        count
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.read = 0;
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_and_pop() {
+        let mut buffer = SampleRingBuffer::new(4);
+        assert!(buffer.is_empty());
+        buffer.push(0.1);
+        buffer.push(0.2);
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(buffer.pop(), Some(0.1));
+        assert_eq!(buffer.pop(), Some(0.2));
+        assert_eq!(buffer.pop(), None);
+    }
+
+    #[test]
+    fn test_overwrites_oldest_when_full() {
+        let mut buffer = SampleRingBuffer::new(3);
+        for sample in [0.1, 0.2, 0.3, 0.4, 0.5] {
+            buffer.push(sample);
+        }
+        assert_eq!(buffer.len(), 3);
+        let mut out = [0.0; 4];
+        assert_eq!(buffer.drain_f32(&mut out), 3);
+        assert_eq!(out[..3], [0.3, 0.4, 0.5]);
+    }
+
+    #[test]
+    fn test_drain_i16() {
+        let mut buffer = SampleRingBuffer::new(8);
+        for sample in [0.0, 0.5, -1.0, 2.0] {
+            buffer.push(sample);
+        }
+        let mut out = [0; 2];
+        assert_eq!(buffer.drain_i16(&mut out), 2);
+        assert_eq!(out, [0, 16383]);
+        assert_eq!(buffer.drain_i16(&mut out), 2);
+        assert_eq!(out, [-32767, 32767]);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut buffer = SampleRingBuffer::new(2);
+        buffer.push(0.1);
+        buffer.clear();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.pop(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity must be non-zero")]
+    fn test_zero_capacity() {
+        SampleRingBuffer::new(0);
+    }
+}
@@ -0,0 +1,131 @@
+// 矩形波1と2では周期を下げる計算が異なる
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PulseChannel {
+    // 1の補数で減算する (変化量 + 1 だけ下がる)
+    Pulse1,
+    // 2の補数で減算する
+    Pulse2,
+}
+
+// 矩形波の周期を自動で上下させるユニット。ハーフフレームごとに動く
+#[derive(Debug, PartialEq)]
+pub struct Sweep {
+    pub channel: PulseChannel,
+    pub enabled: bool,
+    pub period: u8,
+    pub negate: bool,
+    pub shift: u8,
+    reload: bool,
+    divider: u8,
+}
+
+impl Sweep {
+    pub fn new(channel: PulseChannel) -> Self {
+        Sweep {
+            channel,
+            enabled: false,
+            period: 0,
+            negate: false,
+            shift: 0,
+            reload: false,
+            divider: 0,
+        }
+    }
+
+    // EPPP NSSS
+    pub fn write(&mut self, data: u8) {
+        self.enabled = data & 0b1000_0000 != 0;
+        self.period = (data >> 4) & 0b111;
+        self.negate = data & 0b0000_1000 != 0;
+        self.shift = data & 0b0000_0111;
+        self.reload = true;
+    }
+
+    pub fn target_period(&self, timer_period: u16) -> u16 {
+        let change = timer_period >> self.shift;
+        if !self.negate {
+            return timer_period + change;
+        }
+
+        match self.channel {
+            PulseChannel::Pulse1 => timer_period.saturating_sub(change + 1),
+            PulseChannel::Pulse2 => timer_period.saturating_sub(change),
+        }
+    }
+
+    // スイープが無効でも、周期が範囲外ならチャンネルは消音される
+    pub fn is_muting(&self, timer_period: u16) -> bool {
+        timer_period < 8 || self.target_period(timer_period) > 0x7FF
+    }
+
+    // 新しい周期を返す
+    pub fn clock(&mut self, timer_period: u16) -> u16 {
+        let mut period = timer_period;
+        if self.divider == 0 && self.enabled && self.shift > 0 && !self.is_muting(timer_period) {
+            period = self.target_period(timer_period);
+        }
+
+        if self.divider == 0 || self.reload {
+            self.divider = self.period;
+            self.reload = false;
+        } else {
+            self.divider -= 1;
+        }
+        period
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write() {
+        let mut sweep = Sweep::new(PulseChannel::Pulse1);
+        sweep.write(0b1011_1010);
+        assert!(sweep.enabled);
+        assert_eq!(sweep.period, 3);
+        assert!(sweep.negate);
+        assert_eq!(sweep.shift, 2);
+    }
+
+    #[test]
+    fn test_target_period_negate_ones_complement() {
+        let mut pulse1 = Sweep::new(PulseChannel::Pulse1);
+        let mut pulse2 = Sweep::new(PulseChannel::Pulse2);
+        pulse1.write(0b1000_1001);
+        pulse2.write(0b1000_1001);
+        assert_eq!(pulse1.target_period(0x100), 0x100 - 0x80 - 1);
+        assert_eq!(pulse2.target_period(0x100), 0x100 - 0x80);
+    }
+
+    #[test]
+    fn test_is_muting() {
+        let sweep = Sweep::new(PulseChannel::Pulse2);
+        assert!(sweep.is_muting(7));
+        assert!(!sweep.is_muting(8));
+        // shift = 0 でも加算結果が0x7FFを超えると消音
+        assert!(sweep.is_muting(0x400));
+    }
+
+    #[test]
+    fn test_clock_updates_period() {
+        let mut sweep = Sweep::new(PulseChannel::Pulse2);
+        sweep.write(0b1001_0001); // 周期1, シフト1
+        let period = sweep.clock(0x100);
+        assert_eq!(period, 0x180);
+        // ディバイダが周期1でリロードされるので、次の更新は2回後
+        let period = sweep.clock(period);
+        assert_eq!(period, 0x180);
+        let period = sweep.clock(period);
+        assert_eq!(period, 0x240);
+    }
+
+    #[test]
+    fn test_clock_disabled_keeps_period() {
+        let mut sweep = Sweep::new(PulseChannel::Pulse2);
+        sweep.write(0b0000_0001);
+        assert_eq!(sweep.clock(0x100), 0x100);
+        assert_eq!(sweep.clock(0x100), 0x100);
+    }
+}
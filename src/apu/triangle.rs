@@ -0,0 +1,157 @@
+use super::length_counter::LengthCounter;
+use super::linear_counter::LinearCounter;
+
+#[rustfmt::skip]
+const SEQUENCE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10,  9,  8,  7,  6,  5,  4,  3,  2,  1,  0,
+     0,  1,  2,  3,  4,  5,  6,  7,  8,  9, 10, 11, 12, 13, 14, 15,
+];
+
+// これより短い周期は可聴域を超えるので、シーケンサを止めてポップノイズを避ける
+const ULTRASONIC_PERIOD: u16 = 2;
+
+// 三角波チャンネル ($4008-$400B)
+#[derive(Debug, Default, PartialEq)]
+pub struct Triangle {
+    pub linear_counter: LinearCounter,
+    pub length_counter: LengthCounter,
+    sequence_step: u8,
+    pub timer_period: u16,
+    timer: u16,
+}
+
+impl Triangle {
+    pub fn new() -> Self {
+        Triangle {
+            linear_counter: LinearCounter::new(),
+            length_counter: LengthCounter::new(),
+            sequence_step: 0,
+            timer_period: 0,
+            timer: 0,
+        }
+    }
+
+    pub fn write_register(&mut self, index: u16, data: u8) {
+        match index & 0b11 {
+            // CRRR RRRR
+            0 => {
+                self.linear_counter.write(data);
+                self.length_counter.halt = self.linear_counter.control;
+            }
+            // 未使用 ($4009)
+            1 => {}
+            // TTTT TTTT
+            2 => self.timer_period = (self.timer_period & 0x0700) | data as u16,
+            // LLLL LTTT
+            3 => {
+                self.timer_period = (self.timer_period & 0x00FF) | (((data & 0b111) as u16) << 8);
+                self.length_counter.load(data >> 3);
+                self.linear_counter.reload = true;
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // 三角波のタイマーはCPUサイクルごとに動く
+    pub fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.timer_period;
+        if self.linear_counter.is_active()
+            && self.length_counter.is_active()
+            && self.timer_period >= ULTRASONIC_PERIOD
+        {
+            self.sequence_step = (self.sequence_step + 1) % 32;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.linear_counter.clock();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length_counter.clock();
+    }
+
+    // カウンタが切れてもシーケンサが止まるだけで、直前の値を出力し続ける
+    pub fn output(&self) -> u8 {
+        SEQUENCE[self.sequence_step as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playing_triangle() -> Triangle {
+        let mut triangle = Triangle::new();
+        triangle.length_counter.set_enabled(true);
+        triangle.write_register(0, 0b0111_1111);
+        triangle.write_register(2, 0x04);
+        triangle.write_register(3, 0b0000_1000);
+        triangle.clock_quarter_frame();
+        triangle
+    }
+
+    fn clock_sequence_step(triangle: &mut Triangle) {
+        for _ in 0..=triangle.timer_period {
+            triangle.clock_timer();
+        }
+    }
+
+    #[test]
+    fn test_write_registers() {
+        let triangle = playing_triangle();
+        assert_eq!(triangle.timer_period, 4);
+        assert_eq!(triangle.length_counter.counter, 254);
+        assert_eq!(triangle.linear_counter.counter, 0x7F);
+        assert!(!triangle.length_counter.halt);
+    }
+
+    #[test]
+    fn test_sequence() {
+        let mut triangle = playing_triangle();
+        let mut outputs = vec![triangle.output()];
+        for _ in 0..32 {
+            clock_sequence_step(&mut triangle);
+            outputs.push(triangle.output());
+        }
+        assert_eq!(&outputs[..4], &[15, 14, 13, 12]);
+        assert_eq!(&outputs[15..18], &[0, 0, 1]);
+        assert_eq!(outputs[32], 15);
+    }
+
+    #[test]
+    fn test_halts_without_linear_counter() {
+        let mut triangle = playing_triangle();
+        triangle.linear_counter.counter = 0;
+        clock_sequence_step(&mut triangle);
+        clock_sequence_step(&mut triangle);
+        assert_eq!(triangle.output(), 15);
+    }
+
+    #[test]
+    fn test_halts_at_ultrasonic_period() {
+        let mut triangle = playing_triangle();
+        clock_sequence_step(&mut triangle);
+        assert_eq!(triangle.output(), 14);
+
+        triangle.write_register(2, 0x01);
+        triangle.write_register(3, 0b0000_1000);
+        for _ in 0..10 {
+            triangle.clock_timer();
+        }
+        assert_eq!(triangle.output(), 14);
+    }
+
+    #[test]
+    fn test_control_flag_halts_length_counter() {
+        let mut triangle = playing_triangle();
+        triangle.write_register(0, 0b1000_0001);
+        triangle.clock_half_frame();
+        assert_eq!(triangle.length_counter.counter, 254);
+    }
+}
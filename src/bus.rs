@@ -1,18 +1,98 @@
-use core::panic;
-
+use crate::apu::NesAPU;
 use crate::cpu::Mem;
+use crate::joypad::Joypad;
+use crate::ppu::{NesPPU, DOTS_PER_CPU_CYCLE};
 use crate::rom::Rom;
 
 pub struct Bus {
     pub cpu_vram: [u8; 2048],
     pub rom: Rom,
+    pub ppu: NesPPU,
+    pub apu: NesAPU,
+    pub joypad1: Joypad,
+    pub joypad2: Joypad,
+    pub cycles: usize,
+    // 実行中の命令でCPUがバスにアクセスした回数。APUはアクセスごとに1サイクル進める
+    access_cycles: usize,
+    // OAM DMA/DMC DMAでCPUが停止するサイクル数
+    dma_stall_cycles: usize,
+    // 書き込みサイクルの偶奇が確定していないOAM DMA
+    oam_dma_pending: bool,
 }
 
 impl Bus {
     pub fn new(rom: Rom) -> Self {
+        let ppu = NesPPU::new(rom.chr_rom.clone(), rom.screen_mirroring);
         Self {
             cpu_vram: [0; 2048],
             rom: rom,
+            ppu,
+            apu: NesAPU::new(),
+            joypad1: Joypad::new(),
+            joypad2: Joypad::new(),
+            cycles: 0,
+            access_cycles: 0,
+            dma_stall_cycles: 0,
+            oam_dma_pending: false,
+        }
+    }
+
+    // $XX00-$XXFF の256バイトをOAMに転送する
+    fn oam_dma(&mut self, page: u8) {
+        let start = (page as u16) << 8;
+        for offset in 0..=0xFF {
+            let data = self.read(start + offset);
+            self.ppu.write_to_oam_data(data);
+        }
+
+        // 書き込み完了待ちの1サイクル + 読み書き256回ずつ。
+        // $4014 への書き込みが奇数サイクルなら更に1サイクル待つが、
+        // 命令の途中ではまだサイクル数が進んでいないので、偶奇は次の tick で確定させる
+        self.dma_stall_cycles += OAM_DMA_CYCLES;
+        self.oam_dma_pending = true;
+    }
+
+    // CPUのバスアクセス1回を1サイクルとして、このアクセスの直前のサイクルまでAPUを進める。
+    // 命令の途中で発生したDMCのサンプル要求が、同じ命令の次の読み込みサイクルに割り込めるようにする
+    fn begin_access(&mut self) {
+        if self.access_cycles > 0 {
+            self.apu.tick(1);
+        }
+        self.access_cycles += 1;
+    }
+
+    // DMCのサンプルバッファが空なら1バイト読み込む。読み込んだらtrueを返す
+    fn dmc_dma(&mut self) -> bool {
+        match self.apu.dmc.dma_request() {
+            Some(addr) => {
+                let data = self.read(addr);
+                self.apu.dmc.fill_sample_buffer(data);
+                self.dma_stall_cycles += DMC_DMA_CYCLES;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // DMAを含まないバス上の読み込み
+    fn read(&mut self, addr: u16) -> u8 {
+        match addr {
+            PRG..=PRG_ROM_END => self.read_prg_rom(addr),
+            RAM..=RAM_MIRROR_END => {
+                let mirror_down_addr = addr & 0b0000_0111_1111_1111;
+                self.cpu_vram[mirror_down_addr as usize]
+            }
+            PPU_REGISTERS..=PPU_REGISTERS_MIRROR_END => {
+                let mirror_down_addr = addr & 0b0010_0000_0000_0111;
+                self.ppu.read_register(mirror_down_addr)
+            }
+            APU_STATUS => self.apu.read_status(),
+            JOYPAD1 => self.joypad1.read(),
+            JOYPAD2 => self.joypad2.read(),
+            _ => {
+                println!("Ignoring mem access at {:#X}", addr);
+                0
+            }
         }
     }
 
@@ -31,58 +111,150 @@ const RAM: u16 = 0x0000;
 const RAM_MIRROR_END: u16 = 0x1FFF;
 const PPU_REGISTERS: u16 = 0x2000;
 const PPU_REGISTERS_MIRROR_END: u16 = 0x3FFF;
+const APU_REGISTERS: u16 = 0x4000;
+const APU_REGISTERS_END: u16 = 0x4013;
+const OAM_DMA: u16 = 0x4014;
+const APU_STATUS: u16 = 0x4015;
+const APU_FRAME_COUNTER: u16 = 0x4017;
+const OAM_DMA_CYCLES: usize = 513;
+const JOYPAD1: u16 = 0x4016;
+const JOYPAD2: u16 = 0x4017;
+// 停止 + ダミー + アライメント + 読み込み
+const DMC_DMA_CYCLES: usize = 4;
+
+// 読み込みで状態が変わるレジスタ
+fn has_read_side_effects(addr: u16) -> bool {
+    matches!(
+        addr,
+        PPU_REGISTERS..=PPU_REGISTERS_MIRROR_END | APU_STATUS | JOYPAD1 | JOYPAD2
+    )
+}
 
 impl Mem for Bus {
-    fn mem_read(&self, addr: u16) -> u8 {
+    fn mem_read(&mut self, addr: u16) -> u8 {
+        self.begin_access();
+        // DMC DMAはCPUの読み込みサイクルで割り込む。停止中もCPUは同じアドレスを読み直すので、
+        // $2007 ではアドレスが余分に進み、$2002 ではVBlankフラグが先に消え、
+        // $4016/$4017 ではコントローラのシフトレジスタが1ビット進む (ビット欠け)
+        if self.dmc_dma() && has_read_side_effects(addr) {
+            self.read(addr);
+        }
+        self.read(addr)
+    }
+    fn mem_write(&mut self, addr: u16, data: u8) {
+        self.begin_access();
         match addr {
-            PRG..=PRG_ROM_END => self.read_prg_rom(addr),
+            PRG..=PRG_ROM_END => panic!("Attemt to write to Cartrige Rom Spase addr:{:X}", addr),
             RAM..=RAM_MIRROR_END => {
                 let mirror_down_addr = addr & 0b0000_0111_1111_1111;
-                self.cpu_vram[mirror_down_addr as usize]
+                self.cpu_vram[mirror_down_addr as usize] = data;
             }
             PPU_REGISTERS..=PPU_REGISTERS_MIRROR_END => {
                 let mirror_down_addr = addr & 0b0010_0000_0000_0111;
-                todo!("PPU is not implemented yet");
+                self.ppu.write_register(mirror_down_addr, data);
             }
-            _ => {
-                println!("Ignoring mem access at {:#X}", addr);
-                0
+            OAM_DMA => self.oam_dma(data),
+            JOYPAD1 => {
+                self.joypad1.write(data);
+                self.joypad2.write(data);
+            }
+            APU_REGISTERS..=APU_REGISTERS_END | APU_STATUS | APU_FRAME_COUNTER => {
+                self.apu.write_register(addr, data)
             }
+            _ => println!("Ignoring mem write-access at {:#X}", addr),
         }
     }
-    fn mem_write(&mut self, addr: u16, data: u8) {
+    fn mem_peek(&self, addr: u16) -> u8 {
         match addr {
-            PRG..=PRG_ROM_END => panic!("Attemt to write to Cartrige Rom Spase addr:{:X}", addr),
+            PRG..=PRG_ROM_END => self.read_prg_rom(addr),
             RAM..=RAM_MIRROR_END => {
                 let mirror_down_addr = addr & 0b0000_0111_1111_1111;
-                self.cpu_vram[mirror_down_addr as usize] = data;
+                self.cpu_vram[mirror_down_addr as usize]
             }
             PPU_REGISTERS..=PPU_REGISTERS_MIRROR_END => {
                 let mirror_down_addr = addr & 0b0010_0000_0000_0111;
-                todo!("PPU is not implemented yet")
+                self.ppu.peek_register(mirror_down_addr)
             }
+            APU_STATUS => self.apu.peek_status(),
+            JOYPAD1 => self.joypad1.peek(),
+            JOYPAD2 => self.joypad2.peek(),
+            _ => 0,
+        }
+    }
+    fn tick(&mut self, cycles: usize) {
+        // 書き込みは命令の最終サイクル (開始サイクル + 命令のサイクル数 - 1) で行われる
+        if std::mem::take(&mut self.oam_dma_pending) {
+            let write_cycle = (self.cycles + cycles).saturating_sub(1);
+            self.dma_stall_cycles += write_cycle % 2;
+        }
+        self.cycles += cycles;
+        self.ppu.tick(cycles * DOTS_PER_CPU_CYCLE);
+        // バスアクセスごとに進めた分を除いた残りのサイクルだけAPUを進める。
+        // DMCのサンプル要求はここでは処理せず、CPUの次の読み込みサイクルで処理する
+        let stepped = std::mem::take(&mut self.access_cycles).saturating_sub(1);
+        self.apu.tick(cycles.saturating_sub(stepped));
+    }
+    fn poll_nmi_status(&mut self) -> bool {
+        self.ppu.poll_nmi_interrupt()
+    }
+    fn poll_dma_stall_cycles(&mut self) -> usize {
+        std::mem::take(&mut self.dma_stall_cycles)
+    }
+    fn irq_status(&self) -> bool {
+        self.apu.irq_status()
+    }
+    fn ppu_position(&self) -> (u16, u16) {
+        (self.ppu.scanline(), self.ppu.dot())
+    }
+}
 
-            _ => println!("Ignoring mem write-access at {:#X}", addr),
+// アドレス空間全体をRAMとして扱うバス。CPU単体のテストやテストROMの実行に使う
+pub struct FlatBus {
+    pub memory: Vec<u8>,
+}
+
+impl FlatBus {
+    pub fn new() -> Self {
+        FlatBus {
+            memory: vec![0; 0x10000],
         }
     }
 }
 
+impl Default for FlatBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mem for FlatBus {
+    fn mem_read(&mut self, addr: u16) -> u8 {
+        self.memory[addr as usize]
+    }
+    fn mem_write(&mut self, addr: u16, data: u8) {
+        self.memory[addr as usize] = data;
+    }
+    fn mem_peek(&self, addr: u16) -> u8 {
+        self.memory[addr as usize]
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::fs;
-
     use super::*;
+    use crate::cpu::{ProcessorStatus, CPU};
+    use crate::joypad::JoypadButton;
+    use crate::ppu::register::control::ControlRegister;
+    use crate::rom::test;
 
     fn test_rom() -> Rom {
-        let game_dir = "roms";
-        let game_file = "snake.nes";
-        let game = fs::read(format!("{}/{}", game_dir, game_file)).unwrap();
-        Rom::new(&game).unwrap()
+        // PRG ROM全体を0で埋める
+        test::test_rom(vec![0; 0x8000])
     }
 
     #[test]
     fn test_mem_read_ram() {
-        let bus = Bus::new(test_rom());
+        let mut bus = Bus::new(test_rom());
         let addr = 0x0000;
         let data = bus.mem_read(addr);
         assert_eq!(data, 0);
@@ -90,25 +262,368 @@ mod tests {
 
     #[test]
     fn test_mem_read_ram_mirror() {
-        let bus = Bus::new(test_rom());
+        let mut bus = Bus::new(test_rom());
         let addr = 0x0800;
         let data = bus.mem_read(addr);
         assert_eq!(data, 0);
     }
 
-    // #[test]
-    // fn test_mem_read_ppu_registers() {
-    //     todo!("Implement this test");
-    // }
+    #[test]
+    fn test_mem_peek_ram_mirror() {
+        let mut bus = Bus::new(test_rom());
+        bus.mem_write(0x0001, 0x42);
+        assert_eq!(bus.mem_peek(0x0801), 0x42);
+        assert_eq!(bus.mem_peek(0x1801), 0x42);
+    }
+
+    #[test]
+    fn test_mem_peek_ppu_registers_does_not_panic() {
+        let bus = Bus::new(test_rom());
+        assert_eq!(bus.mem_peek(0x2002), 0);
+    }
+
+    #[test]
+    fn test_mem_read_ppu_registers() {
+        let mut bus = Bus::new(test_rom());
+        bus.mem_write(0x2003, 0x10);
+        bus.mem_write(0x2004, 0x42);
+        bus.mem_write(0x2003, 0x10);
+        assert_eq!(bus.mem_read(0x2004), 0x42);
+        assert_eq!(bus.ppu.oam.data[0x10], 0x42);
+    }
+
+    #[test]
+    fn test_mem_read_ppu_registers_mirror() {
+        let mut bus = Bus::new(test_rom());
+        bus.mem_write(0x200B, 0x10);
+        bus.mem_write(0x3FFC, 0x42);
+        bus.mem_write(0x2FF3, 0x10);
+        assert_eq!(bus.mem_read(0x3004), 0x42);
+    }
+
+    #[test]
+    fn test_mem_write_ppu_ctrl() {
+        let mut bus = Bus::new(test_rom());
+        bus.mem_write(0x2000, 0x80);
+        assert!(bus.ppu.ctrl.contains(ControlRegister::GENERATE_NMI));
+    }
+
+    #[test]
+    fn test_tick_delivers_vblank_nmi() {
+        let mut bus = Bus::new(test_rom());
+        bus.mem_write(0x2000, 0x80);
+
+        // スキャンライン241のドット1を過ぎるまでCPUサイクルを進める
+        let cycles_to_vblank = (241 * 341 + 2) / DOTS_PER_CPU_CYCLE + 1;
+        bus.tick(cycles_to_vblank - 1);
+        assert!(!bus.poll_nmi_status());
+        bus.tick(1);
+        assert!(bus.poll_nmi_status());
+        assert_eq!(bus.mem_read(0x2002) & 0x80, 0x80);
+    }
+
+    #[test]
+    fn test_oam_dma() {
+        let mut bus = Bus::new(test_rom());
+        for i in 0..=0xFF {
+            bus.mem_write(0x0200 + i, i as u8);
+        }
+        bus.mem_write(0x2003, 0x10);
+        bus.mem_write(0x4014, 0x02);
+
+        // OAMADDRの位置から書き込まれる
+        assert_eq!(bus.ppu.oam.data[0x10], 0x00);
+        assert_eq!(bus.ppu.oam.data[0x0F], 0xFF);
+        assert_eq!(bus.ppu.oam.addr, 0x10);
+        assert_eq!(bus.poll_dma_stall_cycles(), 513);
+        assert_eq!(bus.poll_dma_stall_cycles(), 0);
+    }
+
+    #[test]
+    fn test_oam_dma_on_odd_cycle() {
+        // サイクル0から始まる2サイクルの命令: 書き込みはサイクル1
+        let mut bus = Bus::new(test_rom());
+        bus.mem_write(0x4014, 0x02);
+        bus.tick(2);
+        assert_eq!(bus.poll_dma_stall_cycles(), 514);
+
+        // サイクル2から始まる1サイクル: 書き込みはサイクル2
+        bus.mem_write(0x4014, 0x02);
+        bus.tick(1);
+        assert_eq!(bus.poll_dma_stall_cycles(), 513);
+    }
+
+    #[test]
+    fn test_oam_dma_stalls_cpu() {
+        // LDA #$02; STA $4014; JAM
+        let bus = Bus::new(test::test_rom(vec![0xa9, 0x02, 0x8d, 0x14, 0x40, 0x02]));
+        let mut cpu = CPU::new(bus);
+        cpu.reset();
+        cpu.program_counter = 0x8000;
+        cpu.run();
+
+        // STAはリセット7 + LDA 2 = 9サイクル目から始まり、
+        // 書き込みは 9 + 4 - 1 = 12サイクル目 (偶数) なので513サイクル止まる
+        assert_eq!(cpu.cycles, 9 + 4 + 513);
+        assert_eq!(cpu.bus.cycles, cpu.cycles);
+    }
+
+    #[test]
+    fn test_oam_dma_stalls_cpu_on_odd_write_cycle() {
+        // LDA #$02; LDX #$00; STA $4014,X; JAM
+        let bus = Bus::new(test::test_rom(vec![
+            0xa9, 0x02, 0xa2, 0x00, 0x9d, 0x14, 0x40, 0x02,
+        ]));
+        let mut cpu = CPU::new(bus);
+        cpu.reset();
+        cpu.program_counter = 0x8000;
+        cpu.run();
 
-    // #[test]
-    // fn test_mem_read_ppu_registers_mirror() {
-    //     todo!("Implement this test");
-    // }
+        // STAはリセット7 + LDA 2 + LDX 2 = 11サイクル目から始まり、
+        // 書き込みは 11 + 5 - 1 = 15サイクル目 (奇数) なので514サイクル止まる
+        assert_eq!(cpu.cycles, 11 + 5 + 514);
+        assert_eq!(cpu.bus.cycles, cpu.cycles);
+    }
+
+    #[test]
+    fn test_apu_registers() {
+        let mut bus = Bus::new(test_rom());
+        bus.mem_write(0x4015, 0b0000_0010);
+        bus.mem_write(0x4004, 0b1011_1111);
+        bus.mem_write(0x4007, 0b0000_1000);
+        assert_eq!(bus.apu.pulse2.length_counter.counter, 254);
+        assert_eq!(bus.mem_peek(0x4015), 0b0000_0010);
+        assert_eq!(bus.mem_read(0x4015), 0b0000_0010);
+    }
+
+    #[test]
+    fn test_tick_clocks_apu() {
+        let mut bus = Bus::new(test_rom());
+        bus.mem_write(0x4015, 0b0000_0001);
+        bus.mem_write(0x4000, 0b0101_1111);
+        bus.mem_write(0x4002, 0x08);
+        bus.mem_write(0x4003, 0b0000_1000);
+        assert_eq!(bus.apu.pulse1.output(), 0);
+        // 4回のアクセスで4サイクル。アクセスの間に進めた3サイクルを除き、
+        // 書き込み後の1サイクル (奇数サイクル) でパルスのタイマーが進む
+        bus.tick(4);
+        assert_eq!(bus.apu.pulse1.output(), 15);
+    }
+
+    // $C000 から始まるDMCサンプルを1バイト再生する設定
+    fn setup_dmc(bus: &mut Bus, control: u8) {
+        bus.mem_write(0x4010, control);
+        bus.mem_write(0x4012, 0x00);
+        bus.mem_write(0x4013, 0x00);
+        bus.mem_write(0x4015, 0b0001_0000);
+    }
+
+    #[test]
+    fn test_dmc_dma_reads_sample_and_stalls() {
+        let mut prg = vec![0; 0x8000];
+        prg[0x4000] = 0xAA;
+        let mut bus = Bus::new(test::test_rom(prg));
+        setup_dmc(&mut bus, 0b1000_0000);
+        assert_eq!(bus.poll_dma_stall_cycles(), 0);
+
+        // 要求は tick では処理されず、CPUの次の読み込みサイクルまで保留される
+        bus.tick(1);
+        assert_eq!(bus.apu.dmc.dma_request(), Some(0xC000));
+        assert_eq!(bus.poll_dma_stall_cycles(), 0);
+
+        bus.mem_read(0x0000);
+        assert_eq!(bus.poll_dma_stall_cycles(), 4);
+        assert!(!bus.apu.dmc.is_active());
+        assert!(bus.irq_status());
+    }
+
+    #[test]
+    fn test_dmc_irq_reaches_cpu() {
+        // CLI; NOP; NOP; ... IRQハンドラ ($9000): JAM
+        let mut prg = vec![0xea; 0x8000];
+        prg[0] = 0x58;
+        prg[0x1000] = 0x02;
+        prg[0x7FFE] = 0x00;
+        prg[0x7FFF] = 0x90;
+        let bus = Bus::new(test::test_rom(prg));
+        let mut cpu = CPU::new(bus);
+        cpu.reset();
+        cpu.program_counter = 0x8000;
+        setup_dmc(&mut cpu.bus, 0b1000_0000);
+        cpu.run();
+
+        assert_eq!(cpu.program_counter, 0x9000);
+    }
+
+    #[test]
+    fn test_dmc_dma_conflicts_with_ppu_data_read() {
+        let mut bus = Bus::new(test_rom());
+        bus.mem_write(0x2006, 0x20);
+        bus.mem_write(0x2006, 0x00);
+        setup_dmc(&mut bus, 0);
+
+        // DMAが重なった読み込みでPPUDATAが余分に読まれ、アドレスが2つ進む
+        bus.mem_read(0x2007);
+        assert_eq!(bus.ppu.loopy.vram_addr(), 0x2002);
+        assert_eq!(bus.poll_dma_stall_cycles(), 4);
+
+        bus.mem_read(0x2007);
+        assert_eq!(bus.ppu.loopy.vram_addr(), 0x2003);
+    }
+
+    #[test]
+    fn test_dmc_dma_serviced_on_joypad_read() {
+        let mut bus = Bus::new(test_rom());
+        setup_dmc(&mut bus, 0);
+        bus.mem_read(0x4016);
+        assert_eq!(bus.apu.dmc.dma_request(), None);
+        assert_eq!(bus.poll_dma_stall_cycles(), 4);
+    }
+
+    // 電源投入直後のDMCタイマーは0なので、出力ユニットはAPUサイクル 0, 428, 856, ... で1ビットずつ進み、
+    // 8回目の 7 * 428 = 2996 サイクル目でサンプルバッファが空になって次のDMAが要求される
+    const DMC_BUFFER_EMPTIED_CYCLE: usize = 7 * 428;
+
+    // ループ再生する1バイトのサンプルを読み込ませ、APUを `apu_cycles` サイクル進めたCPU
+    fn cpu_with_pending_dmc_refetch(program: Vec<u8>, apu_cycles: usize) -> CPU<Bus> {
+        let mut cpu = CPU::new(Bus::new(test::test_rom(program)));
+        // バスアクセスでAPUを進めないように、レジスタへは直接書き込む
+        cpu.bus.apu.write_register(0x4010, 0b0100_0000);
+        cpu.bus.apu.write_register(0x4015, 0b0001_0000);
+        cpu.bus.mem_read(0x0000);
+        cpu.bus.tick(apu_cycles);
+        assert_eq!(cpu.bus.poll_dma_stall_cycles(), 4);
+        cpu.program_counter = 0x8000;
+        cpu
+    }
+
+    #[test]
+    fn test_dmc_dma_lands_on_cpu_ppu_data_read() {
+        // LDA $2007; JAM
+        let program = vec![0xad, 0x07, 0x20, 0x02];
+
+        // LDAの3回目のアクセスの後でバッファが空になり、4回目の $2007 の読み込みにDMAが重なる
+        let mut cpu = cpu_with_pending_dmc_refetch(program.clone(), DMC_BUFFER_EMPTIED_CYCLE - 2);
+        cpu.bus.ppu.write_to_ppu_addr(0x20);
+        cpu.bus.ppu.write_to_ppu_addr(0x00);
+        cpu.run();
+        assert_eq!(cpu.bus.ppu.loopy.vram_addr(), 0x2002);
+        assert_eq!(cpu.cycles, 4 + 4);
+
+        // 1サイクル遅いとオペランドの読み込みにDMAが重なり、$2007 は1回だけ読まれる
+        let mut cpu = cpu_with_pending_dmc_refetch(program, DMC_BUFFER_EMPTIED_CYCLE - 1);
+        cpu.bus.ppu.write_to_ppu_addr(0x20);
+        cpu.bus.ppu.write_to_ppu_addr(0x00);
+        cpu.run();
+        assert_eq!(cpu.bus.ppu.loopy.vram_addr(), 0x2001);
+        assert_eq!(cpu.cycles, 4 + 4);
+    }
+
+    #[test]
+    fn test_dmc_dma_deletes_controller_bit() {
+        // LDA $4016; JAM
+        let program = vec![0xad, 0x16, 0x40, 0x02];
+
+        // 読み直しでAボタンのビットが捨てられ、Bボタンのビットが返る
+        let mut cpu = cpu_with_pending_dmc_refetch(program.clone(), DMC_BUFFER_EMPTIED_CYCLE - 2);
+        cpu.bus
+            .joypad1
+            .set_button_pressed_status(JoypadButton::BUTTON_A, true);
+        cpu.bus.joypad1.write(1);
+        cpu.bus.joypad1.write(0);
+        cpu.run();
+        assert_eq!(cpu.accumulator, 0);
+
+        let mut cpu = cpu_with_pending_dmc_refetch(program, DMC_BUFFER_EMPTIED_CYCLE - 1);
+        cpu.bus
+            .joypad1
+            .set_button_pressed_status(JoypadButton::BUTTON_A, true);
+        cpu.bus.joypad1.write(1);
+        cpu.bus.joypad1.write(0);
+        cpu.run();
+        assert_eq!(cpu.accumulator, 1);
+    }
+
+    // 241ライン目のドット1を処理した時点でVBlankのNMIが発生する
+    const VBLANK_NMI_DOTS: usize = 241 * 341 + 2;
+
+    // $8000: BRK, IRQハンドラ ($9000): NOP; JAM, NMIハンドラ ($A000): JAM
+    fn interrupt_test_rom() -> Rom {
+        let mut prg = vec![0x02; 0x8000];
+        prg[0] = 0x00;
+        prg[0x1000] = 0xEA;
+        prg[0x7FFA..].copy_from_slice(&[0x00, 0xA0, 0x00, 0x80, 0x00, 0x90]);
+        test::test_rom(prg)
+    }
+
+    // 次の命令を始めてから `dots` ドット目でVBlankのNMIが発生するCPU
+    fn cpu_with_vblank_nmi_after(dots: usize) -> CPU<Bus> {
+        let mut cpu = CPU::new(Bus::new(interrupt_test_rom()));
+        cpu.reset();
+        cpu.bus.ppu.write_to_ctrl(0b1000_0000);
+        let (scanline, dot) = cpu.bus.ppu_position();
+        let elapsed = scanline as usize * 341 + dot as usize;
+        cpu.bus.ppu.tick(VBLANK_NMI_DOTS - elapsed - dots);
+        cpu
+    }
+
+    // スタックの一番上に積まれた割り込みの戻り先とステータス
+    fn top_interrupt_frame(cpu: &CPU<Bus>) -> (u16, u8) {
+        let sp = 0x0100 + cpu.stack_pointer as u16;
+        (cpu.bus.mem_peek_u16(sp + 2), cpu.bus.mem_peek(sp + 1))
+    }
+
+    #[test]
+    fn test_vblank_nmi_hijacks_brk() {
+        // BRKの4サイクル目 (12ドット目) までに来たNMIはベクタを乗っ取る
+        let mut cpu = cpu_with_vblank_nmi_after(12);
+        cpu.run();
+
+        assert_eq!(cpu.program_counter, 0xA000);
+        assert_eq!(cpu.stack_pointer, 0xFA);
+        // 退避内容はBフラグを含めてBRKのまま
+        assert_eq!(top_interrupt_frame(&cpu), (0x8002, 0x34));
+    }
+
+    #[test]
+    fn test_vblank_nmi_after_brk_pushes_pc() {
+        // 乗っ取りの期間を過ぎたNMIは、BRKがIRQベクタへ飛んだ後の命令境界で処理される
+        let mut cpu = cpu_with_vblank_nmi_after(13);
+        cpu.run();
+
+        assert_eq!(cpu.program_counter, 0xA000);
+        assert_eq!(cpu.stack_pointer, 0xF7);
+        assert_eq!(top_interrupt_frame(&cpu), (0x9000, 0x24));
+    }
+
+    #[test]
+    fn test_vblank_nmi_hijacks_irq() {
+        let mut cpu = cpu_with_vblank_nmi_after(12);
+        cpu.status.remove(ProcessorStatus::INTERRUPT_DISABLE);
+        cpu.set_irq_line(true);
+        cpu.run();
+
+        assert_eq!(cpu.program_counter, 0xA000);
+        assert_eq!(cpu.stack_pointer, 0xFA);
+        assert_eq!(top_interrupt_frame(&cpu), (0x8000, 0x20));
+    }
+
+    #[test]
+    fn test_joypad_registers() {
+        let mut bus = Bus::new(test_rom());
+        bus.joypad1
+            .set_button_pressed_status(JoypadButton::BUTTON_B, true);
+        bus.mem_write(0x4016, 1);
+        bus.mem_write(0x4016, 0);
+        assert_eq!(bus.mem_peek(0x4016), 0);
+        assert_eq!(bus.mem_read(0x4016), 0);
+        assert_eq!(bus.mem_read(0x4016), 1);
+        assert_eq!(bus.mem_read(0x4017), 0);
+    }
 
     #[test]
     fn test_mem_read_invalid_address() {
-        let bus = Bus::new(test_rom());
+        let mut bus = Bus::new(test_rom());
         let addr = 0xFFFF;
         let data = bus.mem_read(addr);
         assert_eq!(data, 0);
@@ -140,4 +655,20 @@ mod tests {
         let data = 0x42;
         bus.mem_write(addr, data)
     }
+
+    #[test]
+    fn test_flat_bus_load_and_run() {
+        let mut cpu = CPU::new(FlatBus::new());
+        // LDA #$42; STA $0200; JAM
+        cpu.load_and_run(vec![0xA9, 0x42, 0x8D, 0x00, 0x02, 0x02]);
+
+        assert_eq!(cpu.bus.memory[0x0200], 0x42);
+        assert_eq!(
+            cpu.bus.memory[0x8000..0x8006],
+            [0xA9, 0x42, 0x8D, 0x00, 0x02, 0x02]
+        );
+        // リセットベクタはプログラムの先頭を指す
+        assert_eq!(cpu.mem_read_u16(0xFFFC), 0x8000);
+        assert_eq!(cpu.program_counter, 0x8005);
+    }
 }
@@ -1,7 +1,10 @@
-use crate::opcodes::{self, Operation::*, OPCODES_MAP};
+use crate::opcodes::{self, Operation, Operation::*, OPCODES_MAP};
 use bitflags::bitflags;
 extern crate log;
-use crate::bus::Bus;
+
+pub mod interrupt;
+
+use interrupt::{Interrupt, InterruptType};
 
 bitflags! {
     #[derive(Clone,Copy)]
@@ -37,50 +40,114 @@ pub enum AddressingMode {
 }
 
 pub trait Mem {
-    fn mem_read(&self, addr: u16) -> u8;
+    // I/Oレジスタの読み込みは副作用(フラグのクリアやバッファの更新)を伴う
+    fn mem_read(&mut self, addr: u16) -> u8;
     fn mem_write(&mut self, addr: u16, data: u8);
-    fn mem_read_u16(&self, pos: u16) -> u16 {
+    // 副作用なしで値を覗き見る。トレースやデバッガ用
+    fn mem_peek(&self, addr: u16) -> u8;
+    fn mem_read_u16(&mut self, pos: u16) -> u16 {
         let lo = self.mem_read(pos) as u16;
-        let hi = self.mem_read(pos + 1) as u16;
+        let hi = self.mem_read(pos.wrapping_add(1)) as u16;
         (hi << 8) | (lo as u16)
     }
+    fn mem_peek_u16(&self, pos: u16) -> u16 {
+        let lo = self.mem_peek(pos) as u16;
+        let hi = self.mem_peek(pos.wrapping_add(1)) as u16;
+        (hi << 8) | lo
+    }
     fn mem_write_u16(&mut self, pos: u16, data: u16) {
         let hi = (data >> 8) as u8;
         let lo = (data & 0xff) as u8;
         self.mem_write(pos, lo);
-        self.mem_write(pos + 1, hi);
+        self.mem_write(pos.wrapping_add(1), hi);
+    }
+    // バスにつながった周辺機器をCPUサイクル分進める
+    fn tick(&mut self, _cycles: usize) {}
+    // 周辺機器からのNMI要求を取り出す
+    fn poll_nmi_status(&mut self) -> bool {
+        false
+    }
+    // DMAでCPUが停止するサイクル数を取り出す
+    fn poll_dma_stall_cycles(&mut self) -> usize {
+        0
+    }
+    // 周辺機器のIRQライン。レベルトリガなので取り出さずに状態だけを見る
+    fn irq_status(&self) -> bool {
+        false
+    }
+    // トレース用にPPUの現在位置 (scanline, dot) を返す。PPUがないバスでは常に原点
+    fn ppu_position(&self) -> (u16, u16) {
+        (0, 0)
+    }
+}
+
+// 不安定な非公式命令の挙動は個体差があるため設定で切り替えられるようにする
+#[derive(Debug, Clone, Copy)]
+pub struct UnstableOpConfig {
+    // ANE ($8B): A = (A | magic) & X & imm
+    pub ane_magic: u8,
+    // LXA ($AB): A = X = (A | magic) & imm
+    pub lxa_magic: u8,
+    // SHA/SHX/SHY/SHS: 書き込む値を (アドレス上位バイト + 1) でANDするか
+    pub sh_and_high_byte: bool,
+}
+
+impl Default for UnstableOpConfig {
+    fn default() -> Self {
+        UnstableOpConfig {
+            ane_magic: 0xEE,
+            lxa_magic: 0xEE,
+            sh_and_high_byte: true,
+        }
     }
 }
 
-pub struct CPU {
+pub struct CPU<B: Mem> {
     pub accumulator: u8,
     pub status: ProcessorStatus,
     pub program_counter: u16,
     pub index_register_x: u8,
     pub index_register_y: u8,
     pub stack_pointer: u8,
-    pub bus: Bus,
+    pub cycles: usize,
+    pub halted: bool,
+    // 2A03はBCD演算を持たない。汎用の6502として使う場合に有効にする
+    pub decimal_mode: bool,
+    pub unstable_ops: UnstableOpConfig,
+    pub bus: B,
+    nmi_pending: bool,
+    irq_line: bool,
+    // 命令の途中で既にバスへ進めたサイクル数
+    ticked_cycles: usize,
 }
 
-impl Mem for CPU {
-    fn mem_read(&self, addr: u16) -> u8 {
+impl<B: Mem> Mem for CPU<B> {
+    fn mem_read(&mut self, addr: u16) -> u8 {
         self.bus.mem_read(addr)
     }
     fn mem_write(&mut self, addr: u16, data: u8) {
         self.bus.mem_write(addr, data)
     }
 
-    fn mem_read_u16(&self, pos: u16) -> u16 {
+    fn mem_peek(&self, addr: u16) -> u8 {
+        self.bus.mem_peek(addr)
+    }
+
+    fn mem_read_u16(&mut self, pos: u16) -> u16 {
         self.bus.mem_read_u16(pos)
     }
 
+    fn mem_peek_u16(&self, pos: u16) -> u16 {
+        self.bus.mem_peek_u16(pos)
+    }
+
     fn mem_write_u16(&mut self, pos: u16, data: u16) {
         self.bus.mem_write_u16(pos, data)
     }
 }
 
-impl CPU {
-    pub fn new(bus: Bus) -> Self {
+impl<B: Mem> CPU<B> {
+    pub fn new(bus: B) -> Self {
         CPU {
             accumulator: 0,
             status: ProcessorStatus::empty(),
@@ -88,11 +155,18 @@ impl CPU {
             index_register_x: 0,
             index_register_y: 0,
             stack_pointer: 0xFD,
+            cycles: 0,
+            halted: false,
+            decimal_mode: false,
+            unstable_ops: UnstableOpConfig::default(),
             bus: bus,
+            nmi_pending: false,
+            irq_line: false,
+            ticked_cycles: 0,
         }
     }
 
-    pub fn get_operand_address(&self, mode: &AddressingMode) -> u16 {
+    pub fn get_operand_address(&mut self, mode: &AddressingMode) -> u16 {
         match mode {
             AddressingMode::Immediate => self.program_counter,
 
@@ -169,12 +243,81 @@ impl CPU {
         self.index_register_y = 0;
         self.stack_pointer = 0xfd;
         self.status = ProcessorStatus::from_bits_truncate(0x24);
+        // リセットシーケンスは7サイクルかかる
+        self.cycles = 7;
+        self.bus.tick(self.cycles);
+        self.halted = false;
+        self.nmi_pending = false;
+        self.irq_line = false;
 
         self.program_counter = self.mem_read_u16(0xfffc);
     }
 
+    // NMIはエッジトリガなので、次の命令境界で一度だけ処理される
+    pub fn trigger_nmi(&mut self) {
+        self.nmi_pending = true;
+    }
+
+    // IRQはレベルトリガなので、ラインが下がるまでI=0の間は繰り返し処理される
+    pub fn set_irq_line(&mut self, active: bool) {
+        self.irq_line = active;
+    }
+
+    fn interrupt(&mut self, interrupt: Interrupt) {
+        self.push_u16(self.program_counter);
+
+        // PCを積み終えるまでバスを進め、その間にPPUから届いたNMIを拾う
+        self.bus.tick(interrupt::HIJACK_WINDOW_CYCLES);
+        self.ticked_cycles += interrupt::HIJACK_WINDOW_CYCLES;
+        if self.bus.poll_nmi_status() {
+            self.trigger_nmi();
+        }
+
+        let mut status = self.status;
+        status.set(ProcessorStatus::BREAK, interrupt.b_flag);
+        status.insert(ProcessorStatus::BREAK_2);
+        self.push(status.bits());
+
+        self.status.insert(ProcessorStatus::INTERRUPT_DISABLE);
+
+        // BRK/IRQのスタック退避中にNMIが来るとベクタだけNMIのものに乗っ取られる
+        let vector_addr = if interrupt.itype != InterruptType::NMI && self.nmi_pending {
+            self.nmi_pending = false;
+            interrupt::NMI.vector_addr
+        } else {
+            interrupt.vector_addr
+        };
+
+        self.cycles += interrupt.cpu_cycles as usize;
+        self.program_counter = self.mem_read_u16(vector_addr);
+    }
+
+    fn handle_interrupts(&mut self) {
+        // JAMで停止したCPUはリセット以外受け付けない
+        if self.halted {
+            return;
+        }
+
+        if self.nmi_pending {
+            self.nmi_pending = false;
+            self.interrupt(interrupt::NMI);
+        } else if (self.irq_line || self.bus.irq_status())
+            && !self.status.contains(ProcessorStatus::INTERRUPT_DISABLE)
+        {
+            self.interrupt(interrupt::IRQ);
+        }
+    }
+
     pub fn load(&mut self, program: Vec<u8>) {
-        self.bus.cpu_vram[0x8000..(0x8000 + program.len())].copy_from_slice(&program[..]);
+        // $8000-$FFFF の32KiBを超えるとアドレスが桁あふれする
+        assert!(
+            program.len() <= 0x8000,
+            "program of {} bytes does not fit in $8000-$FFFF",
+            program.len()
+        );
+        for (i, &byte) in program.iter().enumerate() {
+            self.mem_write(0x8000 + i as u16, byte);
+        }
         self.mem_write_u16(0xfffc, 0x8000);
     }
 
@@ -250,10 +393,7 @@ impl CPU {
     }
 
     fn inc(&mut self, mode: &AddressingMode) {
-        let addr = self.get_operand_address(mode);
-        let value = self.mem_read(addr);
-        let incremented_value = value.wrapping_add(1);
-        self.mem_write(addr, incremented_value);
+        let incremented_value = self.read_modify_write(mode, |_, value| value.wrapping_add(1));
         self.update_zero_and_negative_flags(incremented_value);
     }
 
@@ -268,10 +408,7 @@ impl CPU {
     }
 
     fn dec(&mut self, mode: &AddressingMode) {
-        let addr = self.get_operand_address(mode);
-        let value = self.mem_read(addr);
-        let decremented_value = value.wrapping_sub(1);
-        self.mem_write(addr, decremented_value);
+        let decremented_value = self.read_modify_write(mode, |_, value| value.wrapping_sub(1));
         self.update_zero_and_negative_flags(decremented_value);
     }
 
@@ -287,7 +424,12 @@ impl CPU {
 
     fn adc(&mut self, mode: &AddressingMode) {
         let value = self.fetch_data(mode);
+        self.add_with_carry(value);
+    }
+
+    fn add_with_carry(&mut self, value: u8) {
         let acc = self.accumulator;
+        let carry_in = self.status_bit(&ProcessorStatus::CARRY);
 
         let (result, carry1) = value.overflowing_add(self.accumulator);
         let (final_result, carry2) =
@@ -301,11 +443,36 @@ impl CPU {
             (acc ^ value) & 0x80 == 0 && (acc ^ final_result) & 0x80 != 0,
         );
         self.update_zero_and_negative_flags(self.accumulator);
+
+        if self.is_decimal_arithmetic() {
+            self.adc_decimal(acc, value, carry_in);
+        }
+    }
+
+    // NMOS 6502のBCD加算。N/V/Zは2進の結果のまま
+    fn adc_decimal(&mut self, acc: u8, value: u8, carry_in: u8) {
+        let mut lo = (acc & 0x0F) + (value & 0x0F) + carry_in;
+        let mut hi = (acc >> 4) + (value >> 4);
+        if lo > 9 {
+            lo += 6;
+            hi += 1;
+        }
+        if hi > 9 {
+            hi += 6;
+        }
+
+        self.status.set(ProcessorStatus::CARRY, hi > 0x0F);
+        self.accumulator = (hi << 4) | (lo & 0x0F);
     }
 
     fn sbc(&mut self, mode: &AddressingMode) {
         let value = self.fetch_data(mode);
+        self.subtract_with_borrow(value);
+    }
+
+    fn subtract_with_borrow(&mut self, value: u8) {
         let acc = self.accumulator;
+        let borrow = 1 - self.status_bit(&ProcessorStatus::CARRY);
 
         let (result, carry1) = acc.overflowing_sub(value);
         let (final_result, carry2) =
@@ -319,6 +486,29 @@ impl CPU {
             (acc ^ value) & 0x80 != 0 && (acc ^ final_result) & 0x80 != 0,
         );
         self.update_zero_and_negative_flags(self.accumulator);
+
+        if self.is_decimal_arithmetic() {
+            self.sbc_decimal(acc, value, borrow);
+        }
+    }
+
+    // NMOS 6502のBCD減算。キャリーとN/V/Zは2進の結果のまま
+    fn sbc_decimal(&mut self, acc: u8, value: u8, borrow: u8) {
+        let mut lo = (acc & 0x0F) as i16 - (value & 0x0F) as i16 - borrow as i16;
+        let mut hi = (acc >> 4) as i16 - (value >> 4) as i16;
+        if lo < 0 {
+            lo -= 6;
+            hi -= 1;
+        }
+        if hi < 0 {
+            hi -= 6;
+        }
+
+        self.accumulator = ((hi as u8) << 4) | (lo as u8 & 0x0F);
+    }
+
+    fn is_decimal_arithmetic(&self) -> bool {
+        self.decimal_mode && self.status.contains(ProcessorStatus::DECIMAL)
     }
 
     fn and(&mut self, mode: &AddressingMode) {
@@ -343,129 +533,88 @@ impl CPU {
     }
 
     fn asl(&mut self, mode: &AddressingMode) {
-        let mut value = self.fetch_data(mode);
-
-        if value >> 7 == 1 {
-            self.status.insert(ProcessorStatus::CARRY);
-        } else {
-            self.status.remove(ProcessorStatus::CARRY);
-        }
-
-        value = value << 1;
-
-        match mode {
-            AddressingMode::Accumulator => self.accumulator = value,
-            _ => {
-                let addr = self.get_operand_address(mode);
-                self.mem_write(addr, value);
-            }
-        }
-
+        let value = self.read_modify_write(mode, Self::shift_left);
         self.update_zero_and_negative_flags(value);
     }
 
     fn lsr(&mut self, mode: &AddressingMode) {
-        let value = match mode {
-            AddressingMode::Accumulator => self.accumulator,
-            _ => self.fetch_data(mode),
-        };
-
-        self.status.set(ProcessorStatus::CARRY, value & 0x01 == 1);
-
-        let negative_flag = value >> 1;
-        self.update_zero_and_negative_flags(negative_flag);
-
-        match mode {
-            AddressingMode::Accumulator => self.accumulator = negative_flag,
-            _ => {
-                let addr = self.get_operand_address(mode);
-                self.mem_write(addr, negative_flag)
-            }
-        }
+        let value = self.read_modify_write(mode, Self::shift_right);
+        self.update_zero_and_negative_flags(value);
     }
 
     fn rol(&mut self, mode: &AddressingMode) {
-        let mut value = self.fetch_data(mode);
-
-        let old_carry = self.status.contains(ProcessorStatus::CARRY);
-
-        if value >> 7 == 1 {
-            self.status.insert(ProcessorStatus::CARRY);
-        } else {
-            self.status.remove(ProcessorStatus::CARRY);
-        }
+        let value = self.read_modify_write(mode, Self::rotate_left);
+        self.update_zero_and_negative_flags(value);
+    }
 
-        value = value << 1;
+    fn ror(&mut self, mode: &AddressingMode) {
+        let value = self.read_modify_write(mode, Self::rotate_right);
+        self.update_zero_and_negative_flags(value);
+    }
 
-        if old_carry {
-            value = value | 1;
-        }
+    fn shift_left(&mut self, value: u8) -> u8 {
+        self.status.set(ProcessorStatus::CARRY, value >> 7 == 1);
+        value << 1
+    }
 
-        match mode {
-            AddressingMode::Accumulator => {
-                self.accumulator = value;
-            }
-            _ => {
-                let addr = self.get_operand_address(mode);
-                self.mem_write(addr, value)
-            }
-        }
+    fn shift_right(&mut self, value: u8) -> u8 {
+        self.status.set(ProcessorStatus::CARRY, value & 0x01 == 1);
+        value >> 1
+    }
 
-        self.update_negative_flags(value);
+    fn rotate_left(&mut self, value: u8) -> u8 {
+        let old_carry = self.status_bit(&ProcessorStatus::CARRY);
+        self.status.set(ProcessorStatus::CARRY, value >> 7 == 1);
+        (value << 1) | old_carry
     }
 
-    fn ror(&mut self, mode: &AddressingMode) {
-        let mut value = self.fetch_data(mode);
+    fn rotate_right(&mut self, value: u8) -> u8 {
         let old_carry = self.status.contains(ProcessorStatus::CARRY);
-
-        if value & 1 == 1 {
-            self.status.insert(ProcessorStatus::CARRY);
-        } else {
-            self.status.remove(ProcessorStatus::CARRY);
-        }
-        value = value >> 1;
+        self.status.set(ProcessorStatus::CARRY, value & 0x01 == 1);
         if old_carry {
-            value = value | 0b10000000;
+            (value >> 1) | 0b1000_0000
+        } else {
+            value >> 1
         }
+    }
 
-        match mode {
-            AddressingMode::Accumulator => {
-                self.accumulator = value;
-            }
-            _ => {
-                let addr = self.get_operand_address(mode);
-                self.mem_write(addr, value)
-            }
+    // リードモディファイライト命令: オペランドは一度だけ読む。
+    // 実機は変更前の値を一度書き戻してから結果を書き込むので、そのダミー書き込みも再現する
+    fn read_modify_write<F>(&mut self, mode: &AddressingMode, modify: F) -> u8
+    where
+        F: FnOnce(&mut Self, u8) -> u8,
+    {
+        if let AddressingMode::Accumulator = mode {
+            let result = modify(self, self.accumulator);
+            self.accumulator = result;
+            return result;
         }
 
-        self.update_negative_flags(value);
+        let addr = self.get_operand_address(mode);
+        let value = self.mem_read(addr);
+        self.mem_write(addr, value);
+        let result = modify(self, value);
+        self.mem_write(addr, result);
+        result
     }
 
-    fn update_negative_flags(&mut self, value: u8) {
-        if value & 0b1000_0000 != 0 {
-            self.status.set(ProcessorStatus::NEGATIVE, true);
-        } else {
-            self.status.set(ProcessorStatus::NEGATIVE, false);
+    fn branch(&mut self, status: &ProcessorStatus, condition: bool) {
+        let is_set = self.status.bits() & status.bits() != 0;
+        if is_set != condition {
+            return;
         }
-    }
 
-    fn branch(&mut self, status: &ProcessorStatus, condition: bool) {
         let jmp = self.mem_read(self.program_counter) as i8;
-        if condition {
-            if self.status.bits() & status.bits() != 0 {
-                self.program_counter = self
-                    .program_counter
-                    .wrapping_add(1)
-                    .wrapping_add(jmp as u16);
-            }
-        } else {
-            if self.status.bits() & status.bits() == 0 {
-                self.program_counter = self
-                    .program_counter
-                    .wrapping_add(1)
-                    .wrapping_add(jmp as u16);
-            }
+        let next_addr = self.program_counter.wrapping_add(1);
+        let jump_addr = next_addr.wrapping_add(jmp as u16);
+
+        // 分岐成立で+1、分岐先が別ページなら更に+1
+        self.cycles += 1;
+        if is_page_crossed(next_addr, jump_addr) {
+            self.cycles += 1;
         }
+
+        self.program_counter = jump_addr;
     }
 
     fn bit(&mut self, mode: &AddressingMode) {
@@ -484,13 +633,37 @@ impl CPU {
             .set(ProcessorStatus::OVERFLOW, value & 0b0100_0000 > 0);
     }
 
+    fn operand_page_crossed(&self, mode: &AddressingMode) -> bool {
+        match mode {
+            AddressingMode::Absolute_X => {
+                let base = self.mem_peek_u16(self.program_counter);
+                is_page_crossed(base, base.wrapping_add(self.index_register_x as u16))
+            }
+            AddressingMode::Absolute_Y => {
+                let base = self.mem_peek_u16(self.program_counter);
+                is_page_crossed(base, base.wrapping_add(self.index_register_y as u16))
+            }
+            AddressingMode::Indirect_Y => {
+                let ptr = self.mem_peek(self.program_counter);
+                let lo = self.mem_peek(ptr as u16);
+                let hi = self.mem_peek(ptr.wrapping_add(1) as u16);
+                let base = (hi as u16) << 8 | (lo as u16);
+                is_page_crossed(base, base.wrapping_add(self.index_register_y as u16))
+            }
+            _ => false,
+        }
+    }
+
     fn status_bit(&self, reg: &ProcessorStatus) -> u8 {
         self.status.bits() & reg.bits()
     }
 
     fn compare(&mut self, mode: &AddressingMode, register: u8) {
         let value = self.fetch_data(mode);
+        self.compare_value(register, value);
+    }
 
+    fn compare_value(&mut self, register: u8, value: u8) {
         if register >= value {
             self.status.insert(ProcessorStatus::CARRY);
         } else {
@@ -601,12 +774,9 @@ impl CPU {
     }
 
     fn brk(&mut self) {
-        // TODO 割り込み処理の実装後に実装
-        // self.status.insert(ProcessorStatus::BREAK);
-        // self.push_u16(self.program_counter + 1);
-        // self.php();
-        // self.status.insert(ProcessorStatus::INTERRUPT_DISABLE);
-        // self.program_counter = self.mem_read_u16(0xFFFE);
+        // BRKは2バイト命令として扱われ、パディングバイトの次のアドレスが戻り先になる
+        self.program_counter = self.program_counter.wrapping_add(1);
+        self.interrupt(interrupt::BRK);
     }
 
     fn rti(&mut self) {
@@ -615,18 +785,6 @@ impl CPU {
         return;
     }
 
-    // TODO 割り込み処理の実装後に実装
-    // fn irq(&mut self) {
-    //     if !self.status.contains(ProcessorStatus::INTERRUPT_DISABLE) {
-    //         self.push_u16(self.program_counter);
-    //         self.php();
-    //         self.status
-    //             .remove(ProcessorStatus::INTERRUPT_DISABLE | ProcessorStatus::BREAK);
-    //         self.program_counter = self.mem_read_u16(0xFFFE);
-    //         self.rti()
-    //     }
-    // }
-
     fn rax(&mut self, mode: &AddressingMode) {
         self.lda(mode);
         self.tax();
@@ -638,37 +796,159 @@ impl CPU {
         self.mem_write(addr, value);
     }
 
+    // 複合命令はメモリを一度だけ読み書きし、その結果をそのまま後半の演算に使う
     fn dcp(&mut self, mode: &AddressingMode) {
-        self.dec(mode);
-        self.cmp(mode);
+        let value = self.read_modify_write(mode, |_, value| value.wrapping_sub(1));
+        self.compare_value(self.accumulator, value);
     }
 
     fn isb(&mut self, mode: &AddressingMode) {
-        self.inc(mode);
-        self.sbc(mode);
+        let value = self.read_modify_write(mode, |_, value| value.wrapping_add(1));
+        self.subtract_with_borrow(value);
     }
 
     fn slo(&mut self, mode: &AddressingMode) {
-        self.asl(mode);
-        self.ora(mode);
+        let value = self.read_modify_write(mode, Self::shift_left);
+        self.accumulator |= value;
+        self.update_zero_and_negative_flags(self.accumulator);
     }
 
     fn rla(&mut self, mode: &AddressingMode) {
-        self.rol(mode);
-        self.and(mode);
+        let value = self.read_modify_write(mode, Self::rotate_left);
+        self.accumulator &= value;
+        self.update_zero_and_negative_flags(self.accumulator);
     }
 
     fn sre(&mut self, mode: &AddressingMode) {
-        self.lsr(mode);
-        self.eor(mode);
+        let value = self.read_modify_write(mode, Self::shift_right);
+        self.accumulator ^= value;
+        self.update_zero_and_negative_flags(self.accumulator);
     }
 
     fn rra(&mut self, mode: &AddressingMode) {
-        self.ror(mode);
-        self.adc(mode);
+        let value = self.read_modify_write(mode, Self::rotate_right);
+        self.add_with_carry(value);
+    }
+
+    fn anc(&mut self, mode: &AddressingMode) {
+        self.and(mode);
+        self.status.set(
+            ProcessorStatus::CARRY,
+            self.status.contains(ProcessorStatus::NEGATIVE),
+        );
+    }
+
+    fn asr(&mut self, mode: &AddressingMode) {
+        self.and(mode);
+        self.lsr(&AddressingMode::Accumulator);
     }
 
-    fn fetch_data(&self, mode: &AddressingMode) -> u8 {
+    fn arr(&mut self, mode: &AddressingMode) {
+        self.and(mode);
+        self.ror(&AddressingMode::Accumulator);
+
+        let result = self.accumulator;
+        let bit6 = result & 0b0100_0000 != 0;
+        let bit5 = result & 0b0010_0000 != 0;
+        self.status.set(ProcessorStatus::CARRY, bit6);
+        self.status.set(ProcessorStatus::OVERFLOW, bit6 ^ bit5);
+        self.update_zero_and_negative_flags(result);
+    }
+
+    fn lxa(&mut self, mode: &AddressingMode) {
+        let value = self.fetch_data(mode);
+        let result = (self.accumulator | self.unstable_ops.lxa_magic) & value;
+        self.accumulator = result;
+        self.index_register_x = result;
+        self.update_zero_and_negative_flags(result);
+    }
+
+    fn ane(&mut self, mode: &AddressingMode) {
+        let value = self.fetch_data(mode);
+        self.accumulator =
+            (self.accumulator | self.unstable_ops.ane_magic) & self.index_register_x & value;
+        self.update_zero_and_negative_flags(self.accumulator);
+    }
+
+    fn sbx(&mut self, mode: &AddressingMode) {
+        let value = self.fetch_data(mode);
+        let and_result = self.accumulator & self.index_register_x;
+        let result = and_result.wrapping_sub(value);
+
+        self.status.set(ProcessorStatus::CARRY, and_result >= value);
+        self.index_register_x = result;
+        self.update_zero_and_negative_flags(result);
+    }
+
+    fn lae(&mut self, mode: &AddressingMode) {
+        let value = self.fetch_data(mode) & self.stack_pointer;
+        self.accumulator = value;
+        self.index_register_x = value;
+        self.stack_pointer = value;
+        self.update_zero_and_negative_flags(value);
+    }
+
+    fn sha(&mut self, mode: &AddressingMode) {
+        self.store_and_high_byte(mode, self.accumulator & self.index_register_x);
+    }
+
+    fn shx(&mut self, mode: &AddressingMode) {
+        self.store_and_high_byte(mode, self.index_register_x);
+    }
+
+    fn shy(&mut self, mode: &AddressingMode) {
+        self.store_and_high_byte(mode, self.index_register_y);
+    }
+
+    fn shs(&mut self, mode: &AddressingMode) {
+        self.stack_pointer = self.accumulator & self.index_register_x;
+        self.store_and_high_byte(mode, self.stack_pointer);
+    }
+
+    // SHA/SHX/SHY/SHS 共通: 値を (ベースアドレス上位 + 1) でANDして書き込む。
+    // ページを跨いだ場合は書き込み先の上位バイトもその値に化ける
+    fn store_and_high_byte(&mut self, mode: &AddressingMode, register: u8) {
+        let (base, index) = match mode {
+            AddressingMode::Absolute_X => (
+                self.mem_read_u16(self.program_counter),
+                self.index_register_x,
+            ),
+            AddressingMode::Absolute_Y => (
+                self.mem_read_u16(self.program_counter),
+                self.index_register_y,
+            ),
+            AddressingMode::Indirect_Y => {
+                let ptr = self.mem_read(self.program_counter);
+                let lo = self.mem_read(ptr as u16);
+                let hi = self.mem_read(ptr.wrapping_add(1) as u16);
+                ((hi as u16) << 8 | (lo as u16), self.index_register_y)
+            }
+            _ => panic!("mode {:?} is not supported", mode),
+        };
+
+        let value = if self.unstable_ops.sh_and_high_byte {
+            register & ((base >> 8) as u8).wrapping_add(1)
+        } else {
+            register
+        };
+
+        let addr = base.wrapping_add(index as u16);
+        let addr = if is_page_crossed(base, addr) {
+            (value as u16) << 8 | (addr & 0x00FF)
+        } else {
+            addr
+        };
+
+        self.mem_write(addr, value);
+    }
+
+    fn jam(&mut self) {
+        // PCはJAM命令を指したまま停止する
+        self.program_counter = self.program_counter.wrapping_sub(1);
+        self.halted = true;
+    }
+
+    fn fetch_data(&mut self, mode: &AddressingMode) -> u8 {
         let addr = self.get_operand_address(mode);
         match mode {
             AddressingMode::Accumulator => return addr as u8,
@@ -695,12 +975,20 @@ impl CPU {
 
     pub fn run_with_callback<F>(&mut self, mut callback: F)
     where
-        F: FnMut(&mut CPU),
+        F: FnMut(&mut CPU<B>),
     {
         let ref opcodes = *opcodes::OPCODES_MAP;
 
         loop {
+            let cycles_before = self.cycles;
+            self.handle_interrupts();
+
             callback(self);
+
+            if self.halted {
+                return;
+            }
+
             let code = self.mem_read(self.program_counter);
 
             self.program_counter += 1;
@@ -708,7 +996,11 @@ impl CPU {
 
             let opcode = opcodes
                 .get(&code)
-                .expect(&format!("OpCode: {:x} is not found", code));
+                .unwrap_or_else(|| panic!("OpCode: {:x} is not found", code));
+
+            // 読み込み命令はインデックス加算でページを跨ぐと1サイクル余分にかかる
+            let page_cross_penalty =
+                has_page_cross_penalty(opcode.mnemonic) && self.operand_page_crossed(&opcode.mode);
             match opcode.mnemonic {
                 LDA => self.lda(&opcode.mode),
                 LDX => self.ldx(&opcode.mode),
@@ -737,8 +1029,7 @@ impl CPU {
                 LSR => self.lsr(&opcode.mode),
                 ROL => self.rol(&opcode.mode),
                 ROR => self.ror(&opcode.mode),
-                BRK => return,
-                // BRK => self.brk(),
+                BRK => self.brk(),
                 RTI => self.rti(),
                 BCC => self.branch(&ProcessorStatus::CARRY, false),
                 BCS => self.branch(&ProcessorStatus::CARRY, true),
@@ -775,12 +1066,52 @@ impl CPU {
                 RLA => self.rla(&opcode.mode),
                 SRE => self.sre(&opcode.mode),
                 RRA => self.rra(&opcode.mode),
-                _ => todo!("mnemonic: {:?} ", opcode.mnemonic),
+                ANC => self.anc(&opcode.mode),
+                ASR => self.asr(&opcode.mode),
+                ARR => self.arr(&opcode.mode),
+                LXA => self.lxa(&opcode.mode),
+                ANE => self.ane(&opcode.mode),
+                SBX => self.sbx(&opcode.mode),
+                LAE => self.lae(&opcode.mode),
+                SHA => self.sha(&opcode.mode),
+                SHX => self.shx(&opcode.mode),
+                SHY => self.shy(&opcode.mode),
+                SHS => self.shs(&opcode.mode),
+                JAM => self.jam(),
+            }
+
+            self.cycles += opcode.cycles as usize;
+            if page_cross_penalty {
+                self.cycles += 1;
             }
 
             if program_counter_state == self.program_counter {
                 self.program_counter += (opcode.len - 1) as u16;
             }
+
+            let ticked_cycles = std::mem::take(&mut self.ticked_cycles);
+            self.bus.tick(self.cycles - cycles_before - ticked_cycles);
+
+            let stall_cycles = self.bus.poll_dma_stall_cycles();
+            if stall_cycles > 0 {
+                self.cycles += stall_cycles;
+                self.bus.tick(stall_cycles);
+            }
+
+            if self.bus.poll_nmi_status() {
+                self.trigger_nmi();
+            }
         }
     }
 }
+
+fn is_page_crossed(addr1: u16, addr2: u16) -> bool {
+    addr1 & 0xFF00 != addr2 & 0xFF00
+}
+
+fn has_page_cross_penalty(mnemonic: Operation) -> bool {
+    matches!(
+        mnemonic,
+        ADC | AND | CMP | EOR | LDA | LDX | LDY | ORA | SBC | LAX | LAE | NOP
+    )
+}
@@ -0,0 +1,41 @@
+// 割り込みシーケンスのうちPCを積み終えるまでのサイクル。
+// この間にNMIが来るとBRK/IRQのベクタがNMIのものに乗っ取られる
+pub const HIJACK_WINDOW_CYCLES: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InterruptType {
+    NMI,
+    IRQ,
+    BRK,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Interrupt {
+    pub itype: InterruptType,
+    pub vector_addr: u16,
+    // スタックに積むステータスのBフラグ
+    pub b_flag: bool,
+    // BRKのサイクル数はopcode表側で加算する
+    pub cpu_cycles: u8,
+}
+
+pub const NMI: Interrupt = Interrupt {
+    itype: InterruptType::NMI,
+    vector_addr: 0xFFFA,
+    b_flag: false,
+    cpu_cycles: 7,
+};
+
+pub const IRQ: Interrupt = Interrupt {
+    itype: InterruptType::IRQ,
+    vector_addr: 0xFFFE,
+    b_flag: false,
+    cpu_cycles: 7,
+};
+
+pub const BRK: Interrupt = Interrupt {
+    itype: InterruptType::BRK,
+    vector_addr: 0xFFFE,
+    b_flag: true,
+    cpu_cycles: 0,
+};
@@ -0,0 +1,127 @@
+use bitflags::bitflags;
+
+bitflags! {
+    // $4016/$4017 から読み出される順 (A, B, Select, Start, Up, Down, Left, Right) にビットを割り当てる
+    #[derive(Debug, Default, Clone, Copy, PartialEq)]
+    pub struct JoypadButton: u8 {
+        const BUTTON_A = 1;
+        const BUTTON_B = 1 << 1;
+        const SELECT   = 1 << 2;
+        const START    = 1 << 3;
+        const UP       = 1 << 4;
+        const DOWN     = 1 << 5;
+        const LEFT     = 1 << 6;
+        const RIGHT    = 1 << 7;
+    }
+}
+
+// 標準コントローラ。ボタンの状態を8bitのシフトレジスタで1ビットずつ返す
+#[derive(Debug, Default, PartialEq)]
+pub struct Joypad {
+    // strobe が立っている間は常にAボタンの状態を返し続ける
+    strobe: bool,
+    button_index: u8,
+    // strobe が立っている間にラッチしたボタンの状態。読み込みではこちらをシフトアウトする
+    shift_register: u8,
+    pub button_status: JoypadButton,
+}
+
+impl Joypad {
+    pub fn new() -> Self {
+        Joypad {
+            strobe: false,
+            button_index: 0,
+            shift_register: 0,
+            button_status: JoypadButton::empty(),
+        }
+    }
+
+    // $4016 write: bit0 でボタンの状態をシフトレジスタにラッチする
+    pub fn write(&mut self, data: u8) {
+        let strobe = data & 1 == 1;
+        // strobe が立っている間と、立ち下がった時点の状態をラッチする
+        if strobe || self.strobe {
+            self.shift_register = self.button_status.bits();
+            self.button_index = 0;
+        }
+        self.strobe = strobe;
+    }
+
+    pub fn read(&mut self) -> u8 {
+        let response = self.peek();
+        if !self.strobe && self.button_index <= 7 {
+            self.button_index += 1;
+        }
+        response
+    }
+
+    // シフトレジスタを進めずに次に返す値を覗き見る
+    pub fn peek(&self) -> u8 {
+        // 8ビット読み終えた後は1を返し続ける
+        if self.button_index > 7 {
+            return 1;
+        }
+        // strobe 中はボタンの状態が変わればそのまま反映される
+        if self.strobe {
+            return self.button_status.bits() & 1;
+        }
+        (self.shift_register >> self.button_index) & 1
+    }
+
+    pub fn set_button_pressed_status(&mut self, button: JoypadButton, pressed: bool) {
+        self.button_status.set(button, pressed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_buttons_in_order() {
+        let mut joypad = Joypad::new();
+        joypad.set_button_pressed_status(JoypadButton::BUTTON_A, true);
+        joypad.set_button_pressed_status(JoypadButton::START, true);
+        joypad.set_button_pressed_status(JoypadButton::RIGHT, true);
+        joypad.write(1);
+        joypad.write(0);
+
+        let bits: Vec<u8> = (0..8).map(|_| joypad.read()).collect();
+        assert_eq!(bits, vec![1, 0, 0, 1, 0, 0, 0, 1]);
+        // 8ビットを超えて読むと1が返る
+        assert_eq!(joypad.read(), 1);
+    }
+
+    #[test]
+    fn test_button_change_after_strobe_does_not_affect_read() {
+        let mut joypad = Joypad::new();
+        joypad.set_button_pressed_status(JoypadButton::BUTTON_A, true);
+        joypad.set_button_pressed_status(JoypadButton::DOWN, true);
+        joypad.write(1);
+        joypad.write(0);
+
+        // ラッチ後のボタンの変化は次の strobe まで読み出しに現れない
+        joypad.set_button_pressed_status(JoypadButton::DOWN, false);
+        joypad.set_button_pressed_status(JoypadButton::START, true);
+        let bits: Vec<u8> = (0..8).map(|_| joypad.read()).collect();
+        assert_eq!(bits, vec![1, 0, 0, 0, 0, 1, 0, 0]);
+
+        joypad.write(1);
+        joypad.write(0);
+        let bits: Vec<u8> = (0..8).map(|_| joypad.read()).collect();
+        assert_eq!(bits, vec![1, 0, 0, 1, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_strobe_keeps_returning_button_a() {
+        let mut joypad = Joypad::new();
+        joypad.set_button_pressed_status(JoypadButton::BUTTON_A, true);
+        joypad.write(1);
+        for _ in 0..10 {
+            assert_eq!(joypad.read(), 1);
+        }
+
+        joypad.set_button_pressed_status(JoypadButton::BUTTON_A, false);
+        assert_eq!(joypad.read(), 0);
+    }
+}
@@ -1,5 +1,7 @@
+pub mod apu;
 pub mod bus;
 pub mod cpu;
+pub mod joypad;
 pub mod opcodes;
 pub mod ppu;
 pub mod rom;
@@ -317,3 +317,97 @@ lazy_static! {
 		map
 	};
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 0x00-0xFF の基本サイクル数 (ページ跨ぎ・分岐のペナルティを除く。JAMは0)
+    #[rustfmt::skip]
+    const EXPECTED_CYCLES: [u8; 256] = [
+    //  0  1  2  3  4  5  6  7  8  9  A  B  C  D  E  F
+        7, 6, 0, 8, 3, 3, 5, 5, 3, 2, 2, 2, 4, 4, 6, 6, // 0
+        2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 1
+        6, 6, 0, 8, 3, 3, 5, 5, 4, 2, 2, 2, 4, 4, 6, 6, // 2
+        2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 3
+        6, 6, 0, 8, 3, 3, 5, 5, 3, 2, 2, 2, 3, 4, 6, 6, // 4
+        2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 5
+        6, 6, 0, 8, 3, 3, 5, 5, 4, 2, 2, 2, 5, 4, 6, 6, // 6
+        2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 7
+        2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4, // 8
+        2, 6, 0, 6, 4, 4, 4, 4, 2, 5, 2, 5, 5, 5, 5, 5, // 9
+        2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4, // A
+        2, 5, 0, 5, 4, 4, 4, 4, 2, 4, 2, 4, 4, 4, 4, 4, // B
+        2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6, // C
+        2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // D
+        2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6, // E
+        2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // F
+    ];
+
+    fn expected_len(mode: AddressingMode) -> u8 {
+        match mode {
+            AddressingMode::Implicit | AddressingMode::Accumulator => 1,
+            AddressingMode::Immediate
+            | AddressingMode::ZeroPage
+            | AddressingMode::ZeroPage_X
+            | AddressingMode::ZeroPage_Y
+            | AddressingMode::Indirect_X
+            | AddressingMode::Indirect_Y
+            | AddressingMode::Relative => 2,
+            AddressingMode::Absolute
+            | AddressingMode::Absolute_X
+            | AddressingMode::Absolute_Y
+            | AddressingMode::Indirect => 3,
+            AddressingMode::NoneAddressing => panic!("mode {:?} is not supported", mode),
+        }
+    }
+
+    #[test]
+    fn test_no_duplicate_opcodes() {
+        assert_eq!(CPU_OPS_CODES.len(), 256);
+        assert_eq!(OPCODES_MAP.len(), 256);
+    }
+
+    #[test]
+    fn test_all_opcodes_decode() {
+        for code in 0..=0xFFu8 {
+            let opcode = OPCODES_MAP
+                .get(&code)
+                .unwrap_or_else(|| panic!("OpCode: {:#04X} is not found", code));
+
+            assert_eq!(opcode.code, code);
+            assert_eq!(
+                opcode.len,
+                expected_len(opcode.mode),
+                "unexpected length for {:#04X} {:?}",
+                code,
+                opcode.mnemonic
+            );
+            assert_eq!(
+                opcode.cycles, EXPECTED_CYCLES[code as usize],
+                "unexpected cycles for {:#04X} {:?}",
+                code, opcode.mnemonic
+            );
+        }
+    }
+
+    #[test]
+    fn test_jam_opcodes() {
+        for code in [
+            0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2,
+        ] {
+            assert_eq!(OPCODES_MAP[&code].mnemonic, JAM);
+        }
+    }
+
+    #[test]
+    fn test_unofficial_nops() {
+        for code in [
+            0x1A, 0x3A, 0x5A, 0x7A, 0xDA, 0xFA, 0x80, 0x82, 0x89, 0xC2, 0xE2,
+        ] {
+            let opcode = OPCODES_MAP[&code];
+            assert_eq!(opcode.mnemonic, NOP);
+            assert!(matches!(opcode.group, OpGroup::UnOfficial));
+        }
+    }
+}
@@ -1,7 +1,771 @@
+pub mod frame;
+pub mod palette;
 pub mod register;
+mod render;
+mod sprite;
 
 use std::ops::RangeBounds;
 
-use register::control::ControlRegister;
+use crate::rom::Mirroring;
+use frame::Frame;
 use register::constans::*;
-use register::addr::AddrRegister;
+use register::control::ControlRegister;
+use register::data::DataRegister;
+use register::loopy::LoopyRegister;
+use register::mask::MaskRegister;
+use register::oam::OamRegister;
+use register::status::{StatusRegister, OPEN_BUS_MASK};
+use sprite::{LineSprite, MAX_SPRITES_PER_LINE};
+
+// 本体の2KiBに加えて、4画面カートリッジがカートリッジ側に持つ2KiB分を確保しておく
+const VRAM_SIZE: usize = 4096;
+const PALETTE_TABLE_SIZE: usize = 32;
+const CHR_RAM_SIZE: usize = 8192;
+
+pub const DOTS_PER_CPU_CYCLE: usize = 3;
+pub const DOTS_PER_SCANLINE: u16 = 341;
+pub const SCANLINES_PER_FRAME: u16 = 262;
+pub const VISIBLE_SCANLINES: u16 = 240;
+pub const VBLANK_SCANLINE: u16 = 241;
+pub const PRE_RENDER_SCANLINE: u16 = 261;
+// 次のラインの描画に使う v を取り込むドット (水平・垂直のコピーが終わり、先読みを始める前)
+const LINE_START_V_DOT: u16 = 320;
+// スプライト評価が終わるドット
+const SPRITE_EVALUATION_END_DOT: u16 = 257;
+
+pub struct NesPPU {
+    pub chr_rom: Vec<u8>,
+    // CHR ROMを持たないカートリッジはCHR RAMとして書き込みを許可する
+    pub chr_ram: bool,
+    pub palette_table: [u8; PALETTE_TABLE_SIZE],
+    pub vram: [u8; VRAM_SIZE],
+    pub oam: OamRegister,
+    pub mirroring: Mirroring,
+    pub ctrl: ControlRegister,
+    pub mask: MaskRegister,
+    pub status: StatusRegister,
+    pub loopy: LoopyRegister,
+    pub data: DataRegister,
+    // 最後にPPUのI/Oバスに乗った値。書き込み専用レジスタを読むとこの値が返る
+    io_latch: u8,
+    scanline: u16,
+    dot: u16,
+    pub frame_count: u64,
+    pub frame: Frame,
+    line_start_v: u16,
+    // 次のラインに表示するスプライト (セカンダリOAM)
+    line_sprites: Vec<LineSprite>,
+    // 描画中のラインでスプライト0ヒットが起きるドット
+    sprite_zero_hit_dot: Option<u16>,
+    // CPUに通知する前のNMI要求
+    nmi_interrupt: bool,
+}
+
+impl NesPPU {
+    pub fn new(chr_rom: Vec<u8>, mirroring: Mirroring) -> Self {
+        let chr_ram = chr_rom.is_empty();
+        NesPPU {
+            chr_rom: if chr_ram {
+                vec![0; CHR_RAM_SIZE]
+            } else {
+                chr_rom
+            },
+            chr_ram,
+            palette_table: [0; PALETTE_TABLE_SIZE],
+            vram: [0; VRAM_SIZE],
+            oam: OamRegister::new(),
+            mirroring,
+            ctrl: ControlRegister::new(),
+            mask: MaskRegister::new(),
+            status: StatusRegister::new(),
+            loopy: LoopyRegister::new(),
+            data: DataRegister::new(),
+            io_latch: 0,
+            scanline: 0,
+            dot: 0,
+            frame_count: 0,
+            frame: Frame::new(),
+            line_start_v: 0,
+            line_sprites: Vec::with_capacity(MAX_SPRITES_PER_LINE),
+            sprite_zero_hit_dot: None,
+            nmi_interrupt: false,
+        }
+    }
+
+    // 指定したドット数だけPPUを進める
+    pub fn tick(&mut self, dots: usize) {
+        for _ in 0..dots {
+            self.step();
+        }
+    }
+
+    pub fn scanline(&self) -> u16 {
+        self.scanline
+    }
+
+    pub fn dot(&self) -> u16 {
+        self.dot
+    }
+
+    // CPUがNMIを受け付けたら要求を取り下げる
+    pub fn poll_nmi_interrupt(&mut self) -> bool {
+        std::mem::take(&mut self.nmi_interrupt)
+    }
+
+    fn step(&mut self) {
+        match (self.scanline, self.dot) {
+            (VBLANK_SCANLINE, 1) => {
+                self.status.set_vblank_status(true);
+                if self.ctrl.generate_vblank_nmi() {
+                    self.nmi_interrupt = true;
+                }
+            }
+            (PRE_RENDER_SCANLINE, 1) => {
+                self.status.reset_vblank_status();
+                self.status.set_sprite_zero_hit(false);
+                self.status.set_sprite_overflow(false);
+            }
+            _ => {}
+        }
+
+        let rendering_line = self.scanline < VISIBLE_SCANLINES;
+        if rendering_line && self.dot == 1 {
+            self.render_scanline(self.scanline as usize);
+        }
+
+        if rendering_line && self.sprite_zero_hit_dot == Some(self.dot) {
+            self.status.set_sprite_zero_hit(true);
+            self.sprite_zero_hit_dot = None;
+        }
+
+        if self.dot == SPRITE_EVALUATION_END_DOT && self.mask.is_rendering_enabled() {
+            if rendering_line {
+                self.evaluate_sprites(self.scanline);
+            } else if self.scanline == PRE_RENDER_SCANLINE {
+                // 最初のラインにはスプライトは表示されない
+                self.line_sprites.clear();
+            }
+
+            // ドット257-320の間 OAMADDR は0にリセットされる
+            if rendering_line || self.scanline == PRE_RENDER_SCANLINE {
+                self.oam.write_addr(0);
+            }
+        }
+
+        self.update_scroll_counters(self.scanline, self.dot);
+
+        if (rendering_line || self.scanline == PRE_RENDER_SCANLINE) && self.dot == LINE_START_V_DOT
+        {
+            self.line_start_v = self.loopy.v;
+        }
+
+        self.advance_dot();
+    }
+
+    fn advance_dot(&mut self) {
+        // 奇数フレームはレンダリング中ならプリレンダーラインの最後のドットを飛ばす
+        let skip_last_dot = self.scanline == PRE_RENDER_SCANLINE
+            && self.dot == DOTS_PER_SCANLINE - 2
+            && self.frame_count % 2 == 1
+            && self.mask.is_rendering_enabled();
+
+        self.dot += 1;
+        if self.dot < DOTS_PER_SCANLINE && !skip_last_dot {
+            return;
+        }
+
+        self.dot = 0;
+        self.scanline += 1;
+        if self.scanline == SCANLINES_PER_FRAME {
+            self.scanline = 0;
+            self.frame_count += 1;
+        }
+    }
+
+    // $2000-$2007 (ミラー済み) への書き込み
+    pub fn write_register(&mut self, addr: u16, data: u8) {
+        self.io_latch = data;
+        match addr {
+            0x2000 => self.write_to_ctrl(data),
+            0x2001 => self.write_to_mask(data),
+            // PPUSTATUSは読み込み専用
+            0x2002 => {}
+            0x2003 => self.write_to_oam_addr(data),
+            0x2004 => self.write_to_oam_data(data),
+            0x2005 => self.write_to_scroll(data),
+            0x2006 => self.write_to_ppu_addr(data),
+            0x2007 => self.write_to_data(data),
+            _ => panic!("Unexpected PPU register addr:{:X}", addr),
+        }
+    }
+
+    // $2000-$2007 (ミラー済み) からの読み込み
+    pub fn read_register(&mut self, addr: u16) -> u8 {
+        let data = match addr {
+            0x2002 => self.read_status(),
+            0x2004 => self.read_oam_data(),
+            0x2007 => self.read_data(),
+            // 書き込み専用レジスタはオープンバス
+            0x2000 | 0x2001 | 0x2003 | 0x2005 | 0x2006 => self.io_latch,
+            _ => panic!("Unexpected PPU register addr:{:X}", addr),
+        };
+        self.io_latch = data;
+        data
+    }
+
+    // 副作用なしでレジスタの値を覗き見る
+    pub fn peek_register(&self, addr: u16) -> u8 {
+        match addr {
+            0x2002 => self.peek_status(),
+            0x2004 => self.read_oam_data(),
+            0x2007 => self.peek_data(),
+            0x2000 | 0x2001 | 0x2003 | 0x2005 | 0x2006 => self.io_latch,
+            _ => panic!("Unexpected PPU register addr:{:X}", addr),
+        }
+    }
+
+    // マッパーからミラーリングを切り替える
+    pub fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+
+    pub fn write_to_ctrl(&mut self, value: u8) {
+        let before_nmi_status = self.ctrl.generate_vblank_nmi();
+        self.ctrl.update(value);
+        self.loopy.write_ctrl(value);
+
+        // VBlank中にNMIを有効にすると、その時点でNMIが発生する
+        if !before_nmi_status && self.ctrl.generate_vblank_nmi() && self.status.is_in_vblank() {
+            self.nmi_interrupt = true;
+        }
+    }
+
+    pub fn write_to_mask(&mut self, value: u8) {
+        self.mask.update(value);
+    }
+
+    pub fn write_to_ppu_addr(&mut self, value: u8) {
+        self.loopy.write_addr(value);
+    }
+
+    pub fn write_to_scroll(&mut self, value: u8) {
+        self.loopy.write_scroll(value);
+    }
+
+    pub fn write_to_oam_addr(&mut self, value: u8) {
+        self.oam.write_addr(value);
+    }
+
+    pub fn write_to_oam_data(&mut self, value: u8) {
+        self.oam.write_data(value);
+    }
+
+    pub fn read_oam_data(&self) -> u8 {
+        self.oam.read_data()
+    }
+
+    // 読み込むとVBlankフラグと書き込みラッチ(w)がクリアされる
+    pub fn read_status(&mut self) -> u8 {
+        let data = self.peek_status();
+        self.status.reset_vblank_status();
+        self.loopy.reset_latch();
+        data
+    }
+
+    fn peek_status(&self) -> u8 {
+        self.status.snapshot() | (self.io_latch & OPEN_BUS_MASK)
+    }
+
+    pub fn write_to_data(&mut self, value: u8) {
+        let addr = self.loopy.vram_addr();
+        self.increment_vram_addr();
+        self.write_vram(addr, value);
+    }
+
+    pub fn read_data(&mut self) -> u8 {
+        let addr = self.loopy.vram_addr();
+        self.increment_vram_addr();
+
+        if PALLET_RAM_INDEX_RANGE.start <= addr {
+            // パレットの下には $2F00-$2FFF のネームテーブルが見えている
+            let underlying = self.read_vram(addr - 0x1000);
+            self.data.read_palette(self.read_vram(addr), underlying)
+        } else {
+            let data = self.read_vram(addr);
+            self.data.read_buffered(data)
+        }
+    }
+
+    fn peek_data(&self) -> u8 {
+        let addr = self.loopy.vram_addr();
+        if PALLET_RAM_INDEX_RANGE.start <= addr {
+            self.read_vram(addr)
+        } else {
+            self.data.read_buffer
+        }
+    }
+
+    fn increment_vram_addr(&mut self) {
+        self.loopy.increment(self.ctrl.vram_addr_increment());
+    }
+
+    // レンダリング中のスクロールカウンタ(v)の更新。各ドットで呼び出す
+    //   ドット8,16,...,256 と 328,336: coarse X をインクリメント
+    //   ドット256: Y をインクリメント
+    //   ドット257: t から水平方向のビットをコピー
+    //   プリレンダーラインのドット280-304: t から垂直方向のビットをコピー
+    pub fn update_scroll_counters(&mut self, scanline: u16, dot: u16) {
+        if !self.mask.is_rendering_enabled() {
+            return;
+        }
+
+        let pre_render_line = scanline == PRE_RENDER_SCANLINE;
+        if scanline >= VISIBLE_SCANLINES && !pre_render_line {
+            return;
+        }
+
+        match dot {
+            256 => {
+                self.loopy.increment_coarse_x();
+                self.loopy.increment_y();
+            }
+            257 => self.loopy.copy_horizontal(),
+            280..=304 if pre_render_line => self.loopy.copy_vertical(),
+            1..=255 | 328..=336 if dot.is_multiple_of(8) => self.loopy.increment_coarse_x(),
+            _ => {}
+        }
+    }
+
+    fn read_vram(&self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x1FFF => self.chr_rom[addr as usize],
+            0x2000..=0x3EFF => self.vram[self.mirror_vram_addr(addr) as usize],
+            0x3F00..=0x3FFF => self.palette_table[mirror_palette_addr(addr)],
+            _ => panic!("Unexpected access to mirrored space addr:{:X}", addr),
+        }
+    }
+
+    fn write_vram(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x0000..=0x1FFF => {
+                if self.chr_ram {
+                    self.chr_rom[addr as usize] = value;
+                }
+            }
+            0x2000..=0x3EFF => self.vram[self.mirror_vram_addr(addr) as usize] = value,
+            0x3F00..=0x3FFF => self.palette_table[mirror_palette_addr(addr)] = value,
+            _ => panic!("Unexpected access to mirrored space addr:{:X}", addr),
+        }
+    }
+
+    // ネームテーブルのアドレスをミラーリングに従ってVRAMのインデックスに変換する
+    //
+    //   VERTICAL      HORIZONTAL    FOURSCREEN    SINGLE_SCREEN
+    //   [ A ] [ B ]   [ A ] [ a ]   [ A ] [ B ]   [ A ] [ a ]
+    //   [ a ] [ b ]   [ B ] [ b ]   [ C ] [ D ]   [ a ] [ a ]
+    fn mirror_vram_addr(&self, addr: u16) -> u16 {
+        // $3000-$3EFF は $2000-$2EFF のミラー
+        let addr = if NAME_TABLE_MIRROR_RANGE.contains(&addr) {
+            addr - (NAME_TABLE_MIRROR_RANGE.start - NAME_TABLE_RANGE.start)
+        } else {
+            addr
+        };
+
+        let name_table = match addr {
+            addr if NAME_TABLE_0_RANGE.contains(&addr) => 0,
+            addr if NAME_TABLE_1_RANGE.contains(&addr) => 1,
+            addr if NAME_TABLE_2_RANGE.contains(&addr) => 2,
+            addr if NAME_TABLE_3_RANGE.contains(&addr) => 3,
+            _ => panic!("Unexpected name table addr:{:X}", addr),
+        };
+        let offset = (addr - NAME_TABLE_RANGE.start) % ONE_KB;
+
+        let vram_page = match self.mirroring {
+            Mirroring::VERTICAL => name_table % 2,
+            Mirroring::HORIZONTAL => name_table / 2,
+            Mirroring::FOURSCREEN => name_table,
+            Mirroring::SINGLE_SCREEN_LOWER => 0,
+            Mirroring::SINGLE_SCREEN_UPPER => 1,
+        };
+
+        vram_page * ONE_KB + offset
+    }
+}
+
+// $3F10/$3F14/$3F18/$3F1C は $3F00/$3F04/$3F08/$3F0C のミラー
+fn mirror_palette_addr(addr: u16) -> usize {
+    let index = (addr - PALLET_RAM_INDEX_RANGE.start) as usize % PALETTE_TABLE_SIZE;
+    match index {
+        0x10 | 0x14 | 0x18 | 0x1C => index - 0x10,
+        _ => index,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl NesPPU {
+        fn new_empty_rom() -> Self {
+            NesPPU::new(vec![0; CHR_RAM_SIZE], Mirroring::HORIZONTAL)
+        }
+    }
+
+    fn set_ppu_addr(ppu: &mut NesPPU, addr: u16) {
+        ppu.write_to_ppu_addr((addr >> 8) as u8);
+        ppu.write_to_ppu_addr((addr & 0xFF) as u8);
+    }
+
+    #[test]
+    fn test_new_uses_chr_ram_when_rom_has_no_chr() {
+        let ppu = NesPPU::new(vec![], Mirroring::VERTICAL);
+        assert!(ppu.chr_ram);
+        assert_eq!(ppu.chr_rom.len(), CHR_RAM_SIZE);
+    }
+
+    #[test]
+    fn test_write_to_data() {
+        let mut ppu = NesPPU::new_empty_rom();
+        set_ppu_addr(&mut ppu, 0x2305);
+        ppu.write_to_data(0x66);
+        assert_eq!(ppu.vram[0x0305], 0x66);
+        assert_eq!(ppu.loopy.vram_addr(), 0x2306);
+    }
+
+    #[test]
+    fn test_write_to_data_ignored_for_chr_rom() {
+        let mut ppu = NesPPU::new(vec![0x11; CHR_RAM_SIZE], Mirroring::VERTICAL);
+        set_ppu_addr(&mut ppu, 0x0010);
+        ppu.write_to_data(0x66);
+        assert_eq!(ppu.chr_rom[0x0010], 0x11);
+    }
+
+    #[test]
+    fn test_write_to_data_palette_mirror() {
+        let mut ppu = NesPPU::new_empty_rom();
+        set_ppu_addr(&mut ppu, 0x3F10);
+        ppu.write_to_data(0x21);
+        assert_eq!(ppu.palette_table[0x00], 0x21);
+
+        set_ppu_addr(&mut ppu, 0x3F25);
+        ppu.write_to_data(0x0F);
+        assert_eq!(ppu.palette_table[0x05], 0x0F);
+    }
+
+    fn write_name_table(ppu: &mut NesPPU, addr: u16, value: u8) {
+        set_ppu_addr(ppu, addr);
+        ppu.write_to_data(value);
+    }
+
+    #[test]
+    fn test_vertical_mirroring() {
+        let mut ppu = NesPPU::new(vec![], Mirroring::VERTICAL);
+        write_name_table(&mut ppu, 0x2005, 0x11);
+        write_name_table(&mut ppu, 0x2405, 0x22);
+
+        assert_eq!(ppu.vram[0x0005], 0x11);
+        assert_eq!(ppu.vram[0x0405], 0x22);
+        assert_eq!(ppu.mirror_vram_addr(0x2805), 0x0005);
+        assert_eq!(ppu.mirror_vram_addr(0x2C05), 0x0405);
+    }
+
+    #[test]
+    fn test_horizontal_mirroring() {
+        let mut ppu = NesPPU::new(vec![], Mirroring::HORIZONTAL);
+        write_name_table(&mut ppu, 0x2005, 0x11);
+        write_name_table(&mut ppu, 0x2805, 0x22);
+
+        assert_eq!(ppu.vram[0x0005], 0x11);
+        assert_eq!(ppu.vram[0x0405], 0x22);
+        assert_eq!(ppu.mirror_vram_addr(0x2405), 0x0005);
+        assert_eq!(ppu.mirror_vram_addr(0x2C05), 0x0405);
+    }
+
+    #[test]
+    fn test_four_screen_mirroring() {
+        let ppu = NesPPU::new(vec![], Mirroring::FOURSCREEN);
+        assert_eq!(ppu.mirror_vram_addr(0x2005), 0x0005);
+        assert_eq!(ppu.mirror_vram_addr(0x2405), 0x0405);
+        assert_eq!(ppu.mirror_vram_addr(0x2805), 0x0805);
+        assert_eq!(ppu.mirror_vram_addr(0x2C05), 0x0C05);
+    }
+
+    #[test]
+    fn test_single_screen_mirroring_switched_at_runtime() {
+        let mut ppu = NesPPU::new(vec![], Mirroring::SINGLE_SCREEN_LOWER);
+        for addr in [0x2005, 0x2405, 0x2805, 0x2C05] {
+            assert_eq!(ppu.mirror_vram_addr(addr), 0x0005);
+        }
+
+        ppu.set_mirroring(Mirroring::SINGLE_SCREEN_UPPER);
+        for addr in [0x2005, 0x2405, 0x2805, 0x2C05] {
+            assert_eq!(ppu.mirror_vram_addr(addr), 0x0405);
+        }
+    }
+
+    #[test]
+    fn test_name_table_mirror_range() {
+        let mut ppu = NesPPU::new(vec![], Mirroring::VERTICAL);
+        write_name_table(&mut ppu, 0x2405, 0x22);
+        set_ppu_addr(&mut ppu, 0x3405);
+        ppu.read_data();
+        assert_eq!(ppu.read_data(), 0x22);
+        assert_eq!(ppu.mirror_vram_addr(0x3EFF), ppu.mirror_vram_addr(0x2EFF));
+    }
+
+    #[test]
+    fn test_read_data_is_delayed_by_buffer() {
+        let mut ppu = NesPPU::new_empty_rom();
+        ppu.vram[0x0305] = 0x66;
+        ppu.vram[0x0306] = 0x77;
+        set_ppu_addr(&mut ppu, 0x2305);
+
+        ppu.read_data(); // ダミーリード
+        assert_eq!(ppu.read_data(), 0x66);
+        assert_eq!(ppu.read_data(), 0x77);
+        assert_eq!(ppu.loopy.vram_addr(), 0x2308);
+    }
+
+    #[test]
+    fn test_read_data_step_32() {
+        let mut ppu = NesPPU::new_empty_rom();
+        ppu.write_to_ctrl(0b0000_0100);
+        ppu.vram[0x01FF] = 0x66;
+        ppu.vram[0x01FF + 32] = 0x77;
+        ppu.vram[0x01FF + 64] = 0x88;
+        set_ppu_addr(&mut ppu, 0x21FF);
+
+        ppu.read_data();
+        assert_eq!(ppu.read_data(), 0x66);
+        assert_eq!(ppu.read_data(), 0x77);
+        assert_eq!(ppu.read_data(), 0x88);
+    }
+
+    #[test]
+    fn test_read_data_palette_is_immediate() {
+        let mut ppu = NesPPU::new_empty_rom();
+        ppu.palette_table[0x01] = 0x2A;
+        ppu.vram[0x0701] = 0x55; // $2F01 の下のネームテーブル
+        set_ppu_addr(&mut ppu, 0x3F01);
+
+        assert_eq!(ppu.read_data(), 0x2A);
+        assert_eq!(ppu.data.read_buffer, 0x55);
+    }
+
+    #[test]
+    fn test_peek_data_returns_buffer() {
+        let mut ppu = NesPPU::new_empty_rom();
+        ppu.vram[0x0000] = 0x66;
+        set_ppu_addr(&mut ppu, 0x2000);
+        ppu.read_data();
+        assert_eq!(ppu.peek_register(0x2007), 0x66);
+        assert_eq!(ppu.loopy.vram_addr(), 0x2001);
+    }
+
+    #[test]
+    fn test_oam_data_write_increments_addr() {
+        let mut ppu = NesPPU::new_empty_rom();
+        ppu.write_register(0x2003, 0xFF);
+        ppu.write_register(0x2004, 0x12);
+        ppu.write_register(0x2004, 0x34);
+        assert_eq!(ppu.oam.data[0xFF], 0x12);
+        assert_eq!(ppu.oam.data[0x00], 0x34);
+        assert_eq!(ppu.oam.addr, 0x01);
+    }
+
+    #[test]
+    fn test_read_status_resets_vblank_and_latch() {
+        let mut ppu = NesPPU::new_empty_rom();
+        ppu.status.set_vblank_status(true);
+        ppu.write_to_ppu_addr(0x21);
+
+        let status = ppu.read_register(0x2002);
+        assert_eq!(status & 0x80, 0x80);
+        assert!(!ppu.status.is_in_vblank());
+        assert!(!ppu.loopy.w);
+    }
+
+    #[test]
+    fn test_read_status_low_bits_are_open_bus() {
+        let mut ppu = NesPPU::new_empty_rom();
+        ppu.status.set_sprite_zero_hit(true);
+        ppu.write_register(0x2000, 0x1F);
+        assert_eq!(ppu.read_register(0x2002), 0x5F);
+    }
+
+    #[test]
+    fn test_peek_status_does_not_reset_vblank() {
+        let mut ppu = NesPPU::new_empty_rom();
+        ppu.status.set_vblank_status(true);
+        assert_eq!(ppu.peek_register(0x2002), 0x80);
+        assert!(ppu.status.is_in_vblank());
+    }
+
+    #[test]
+    fn test_scroll_shares_latch_with_ppu_addr() {
+        let mut ppu = NesPPU::new_empty_rom();
+        ppu.write_register(0x2005, 0x12);
+        // 2回目の書き込みとしてPPUADDRの下位バイトに入る
+        ppu.write_register(0x2006, 0x34);
+        assert_eq!(ppu.loopy.x, 0x12 & 0b111);
+        assert_eq!(ppu.loopy.vram_addr(), 0x0034);
+    }
+
+    #[test]
+    fn test_update_scroll_counters_over_a_scanline() {
+        let mut ppu = NesPPU::new_empty_rom();
+        ppu.write_to_mask(0b0000_1000);
+        ppu.write_to_scroll(0b0001_0000); // coarse X = 2
+        ppu.write_to_scroll(0);
+        ppu.loopy.v = ppu.loopy.t;
+
+        for dot in 1..=255 {
+            ppu.update_scroll_counters(0, dot);
+        }
+        // 31タイル進んで右隣のネームテーブルに移っている
+        assert_eq!(ppu.loopy.coarse_x(), 1);
+        assert_eq!(ppu.loopy.name_table(), 0b01);
+
+        ppu.update_scroll_counters(0, 256);
+        assert_eq!(ppu.loopy.fine_y(), 1);
+
+        ppu.update_scroll_counters(0, 257);
+        assert_eq!(ppu.loopy.coarse_x(), 2);
+        assert_eq!(ppu.loopy.name_table(), 0b00);
+
+        for dot in 258..=340 {
+            ppu.update_scroll_counters(0, dot);
+        }
+        // 次のラインの最初の2タイルを先読みしている
+        assert_eq!(ppu.loopy.coarse_x(), 4);
+        assert_eq!(ppu.loopy.fine_y(), 1);
+    }
+
+    #[test]
+    fn test_update_scroll_counters_pre_render_copies_vertical() {
+        let mut ppu = NesPPU::new_empty_rom();
+        ppu.write_to_mask(0b0001_0000);
+        ppu.write_to_scroll(0);
+        ppu.write_to_scroll(0b0101_1011); // coarse Y = 11, fine Y = 3
+        ppu.loopy.v = 0;
+
+        for dot in 257..=304 {
+            ppu.update_scroll_counters(PRE_RENDER_SCANLINE, dot);
+        }
+        assert_eq!(ppu.loopy.coarse_y(), 11);
+        assert_eq!(ppu.loopy.fine_y(), 3);
+    }
+
+    #[test]
+    fn test_update_scroll_counters_disabled_rendering() {
+        let mut ppu = NesPPU::new_empty_rom();
+        for dot in 1..=340 {
+            ppu.update_scroll_counters(0, dot);
+        }
+        assert_eq!(ppu.loopy.v, 0);
+    }
+
+    fn tick_to(ppu: &mut NesPPU, scanline: u16, dot: u16) {
+        while ppu.scanline != scanline || ppu.dot != dot {
+            ppu.tick(1);
+        }
+    }
+
+    #[test]
+    fn test_tick_wraps_scanline_and_frame() {
+        let mut ppu = NesPPU::new_empty_rom();
+        ppu.tick(DOTS_PER_SCANLINE as usize);
+        assert_eq!((ppu.scanline, ppu.dot), (1, 0));
+
+        ppu.tick(DOTS_PER_SCANLINE as usize * (SCANLINES_PER_FRAME as usize - 1));
+        assert_eq!((ppu.scanline, ppu.dot), (0, 0));
+        assert_eq!(ppu.frame_count, 1);
+    }
+
+    #[test]
+    fn test_vblank_set_and_cleared() {
+        let mut ppu = NesPPU::new_empty_rom();
+        tick_to(&mut ppu, VBLANK_SCANLINE, 1);
+        assert!(!ppu.status.is_in_vblank());
+        ppu.tick(1);
+        assert!(ppu.status.is_in_vblank());
+
+        ppu.status.set_sprite_zero_hit(true);
+        tick_to(&mut ppu, PRE_RENDER_SCANLINE, 2);
+        assert!(!ppu.status.is_in_vblank());
+        assert!(!ppu.status.contains(StatusRegister::SPRITE_ZERO_HIT));
+    }
+
+    #[test]
+    fn test_nmi_at_vblank() {
+        let mut ppu = NesPPU::new_empty_rom();
+        ppu.write_to_ctrl(0b1000_0000);
+        tick_to(&mut ppu, VBLANK_SCANLINE, 2);
+        assert!(ppu.poll_nmi_interrupt());
+        assert!(!ppu.poll_nmi_interrupt());
+    }
+
+    #[test]
+    fn test_no_nmi_when_disabled() {
+        let mut ppu = NesPPU::new_empty_rom();
+        tick_to(&mut ppu, VBLANK_SCANLINE, 2);
+        assert!(!ppu.poll_nmi_interrupt());
+    }
+
+    #[test]
+    fn test_nmi_on_enable_during_vblank() {
+        let mut ppu = NesPPU::new_empty_rom();
+        tick_to(&mut ppu, VBLANK_SCANLINE, 10);
+        ppu.write_to_ctrl(0b1000_0000);
+        assert!(ppu.poll_nmi_interrupt());
+
+        // 既に有効なら書き直しても発生しない
+        ppu.write_to_ctrl(0b1000_0000);
+        assert!(!ppu.poll_nmi_interrupt());
+    }
+
+    #[test]
+    fn test_odd_frame_skips_dot_when_rendering() {
+        let mut ppu = NesPPU::new_empty_rom();
+        ppu.write_to_mask(0b0000_1000);
+        let frame_dots = DOTS_PER_SCANLINE as usize * SCANLINES_PER_FRAME as usize;
+
+        ppu.tick(frame_dots);
+        assert_eq!((ppu.frame_count, ppu.scanline, ppu.dot), (1, 0, 0));
+
+        ppu.tick(frame_dots - 1);
+        assert_eq!((ppu.frame_count, ppu.scanline, ppu.dot), (2, 0, 0));
+    }
+
+    #[test]
+    fn test_odd_frame_does_not_skip_dot_without_rendering() {
+        let mut ppu = NesPPU::new_empty_rom();
+        let frame_dots = DOTS_PER_SCANLINE as usize * SCANLINES_PER_FRAME as usize;
+        ppu.tick(frame_dots * 2);
+        assert_eq!((ppu.frame_count, ppu.scanline, ppu.dot), (2, 0, 0));
+    }
+
+    #[test]
+    fn test_write_to_mask() {
+        let mut ppu = NesPPU::new_empty_rom();
+        ppu.write_register(0x2001, 0b0001_1000);
+        assert!(ppu.mask.show_background());
+        assert!(ppu.mask.show_sprites());
+    }
+
+    #[test]
+    fn test_read_write_only_register_returns_latch() {
+        let mut ppu = NesPPU::new_empty_rom();
+        ppu.write_register(0x2000, 0x80);
+        assert_eq!(ppu.read_register(0x2000), 0x80);
+        assert_eq!(ppu.peek_register(0x2006), 0x80);
+    }
+
+    #[test]
+    fn test_peek_register_does_not_increment_addr() {
+        let mut ppu = NesPPU::new_empty_rom();
+        set_ppu_addr(&mut ppu, 0x2000);
+        ppu.peek_register(0x2007);
+        assert_eq!(ppu.loopy.vram_addr(), 0x2000);
+    }
+}
@@ -0,0 +1,43 @@
+pub const WIDTH: usize = 256;
+pub const HEIGHT: usize = 240;
+
+// 1ピクセルごとにNESのカラー番号 ($00-$3F) を保持する
+// bit6-8 にはそのピクセルを描画した時点のPPUMASKの強調ビットが入る
+pub struct Frame {
+    pub data: Vec<u16>,
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Frame::new()
+    }
+}
+
+impl Frame {
+    pub fn new() -> Self {
+        Frame {
+            data: vec![0; WIDTH * HEIGHT],
+        }
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: u16) {
+        self.data[y * WIDTH + x] = color;
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> u16 {
+        self.data[y * WIDTH + x]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_get_pixel() {
+        let mut frame = Frame::new();
+        frame.set_pixel(255, 239, 0x1AA);
+        assert_eq!(frame.get_pixel(255, 239), 0x1AA);
+        assert_eq!(frame.data[WIDTH * HEIGHT - 1], 0x1AA);
+    }
+}
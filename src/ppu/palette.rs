@@ -0,0 +1,181 @@
+use std::fs;
+
+use super::frame::{Frame, HEIGHT, WIDTH};
+
+pub const PALETTE_SIZE: usize = 64;
+// PPUMASKの強調ビット3つ分 (8通り) を含めたパレット
+pub const EMPHASIS_PALETTE_SIZE: usize = PALETTE_SIZE * 8;
+
+// 強調されていない色成分を暗くする割合
+const EMPHASIS_ATTENUATION: f32 = 0.816328;
+
+#[rustfmt::skip]
+pub static SYSTEM_PALETTE: [(u8, u8, u8); PALETTE_SIZE] = [
+   (0x80, 0x80, 0x80), (0x00, 0x3D, 0xA6), (0x00, 0x12, 0xB0), (0x44, 0x00, 0x96), (0xA1, 0x00, 0x5E),
+   (0xC7, 0x00, 0x28), (0xBA, 0x06, 0x00), (0x8C, 0x17, 0x00), (0x5C, 0x2F, 0x00), (0x10, 0x45, 0x00),
+   (0x05, 0x4A, 0x00), (0x00, 0x47, 0x2E), (0x00, 0x41, 0x66), (0x00, 0x00, 0x00), (0x05, 0x05, 0x05),
+   (0x05, 0x05, 0x05), (0xC7, 0xC7, 0xC7), (0x00, 0x77, 0xFF), (0x21, 0x55, 0xFF), (0x82, 0x37, 0xFA),
+   (0xEB, 0x2F, 0xB5), (0xFF, 0x29, 0x50), (0xFF, 0x22, 0x00), (0xD6, 0x32, 0x00), (0xC4, 0x62, 0x00),
+   (0x35, 0x80, 0x00), (0x05, 0x8F, 0x00), (0x00, 0x8A, 0x55), (0x00, 0x99, 0xCC), (0x21, 0x21, 0x21),
+   (0x09, 0x09, 0x09), (0x09, 0x09, 0x09), (0xFF, 0xFF, 0xFF), (0x0F, 0xD7, 0xFF), (0x69, 0xA2, 0xFF),
+   (0xD4, 0x80, 0xFF), (0xFF, 0x45, 0xF3), (0xFF, 0x61, 0x8B), (0xFF, 0x88, 0x33), (0xFF, 0x9C, 0x12),
+   (0xFA, 0xBC, 0x20), (0x9F, 0xE3, 0x0E), (0x2B, 0xF0, 0x35), (0x0C, 0xF0, 0xA4), (0x05, 0xFB, 0xFF),
+   (0x5E, 0x5E, 0x5E), (0x0D, 0x0D, 0x0D), (0x0D, 0x0D, 0x0D), (0xFF, 0xFF, 0xFF), (0xA6, 0xFC, 0xFF),
+   (0xB3, 0xEC, 0xFF), (0xDA, 0xAB, 0xEB), (0xFF, 0xA8, 0xF9), (0xFF, 0xAB, 0xB3), (0xFF, 0xD2, 0xB0),
+   (0xFF, 0xEF, 0xA6), (0xFF, 0xF7, 0x9C), (0xD7, 0xE8, 0x95), (0xA6, 0xED, 0xAF), (0xA2, 0xF2, 0xDA),
+   (0x99, 0xFF, 0xFC), (0xDD, 0xDD, 0xDD), (0x11, 0x11, 0x11), (0x11, 0x11, 0x11),
+];
+
+// PPUのカラー番号 (強調ビット込みで $000-$1FF) をRGBに変換する
+pub struct Palette {
+    colors: Vec<(u8, u8, u8)>,
+}
+
+impl Palette {
+    // .pal ファイルの中身から作る。64色 (192バイト) と 512色 (1536バイト) に対応する
+    // 64色の場合、強調された色は計算で求める
+    pub fn from_pal(data: &[u8]) -> Result<Palette, String> {
+        let colors: Vec<(u8, u8, u8)> = data
+            .chunks_exact(3)
+            .map(|rgb| (rgb[0], rgb[1], rgb[2]))
+            .collect();
+
+        match data.len() {
+            len if len == PALETTE_SIZE * 3 => Ok(Palette::with_emphasis(&colors)),
+            len if len == EMPHASIS_PALETTE_SIZE * 3 => Ok(Palette { colors }),
+            len => Err(format!(
+                "Palette file must be {} or {} bytes, but was {} bytes",
+                PALETTE_SIZE * 3,
+                EMPHASIS_PALETTE_SIZE * 3,
+                len
+            )),
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Palette, String> {
+        let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        Palette::from_pal(&data)
+    }
+
+    fn with_emphasis(base: &[(u8, u8, u8)]) -> Palette {
+        let mut colors = Vec::with_capacity(EMPHASIS_PALETTE_SIZE);
+        for emphasis in 0..8u8 {
+            for (index, &color) in base.iter().enumerate() {
+                colors.push(emphasize(index, color, emphasis));
+            }
+        }
+        Palette { colors }
+    }
+
+    pub fn rgb(&self, color: u16) -> (u8, u8, u8) {
+        self.colors[color as usize % EMPHASIS_PALETTE_SIZE]
+    }
+
+    // SDLのRGB24テクスチャなどに渡すためのバッファに書き出す
+    pub fn write_rgb(&self, frame: &Frame, buffer: &mut [u8]) {
+        for (i, &color) in frame.data.iter().take(WIDTH * HEIGHT).enumerate() {
+            let (r, g, b) = self.rgb(color);
+            buffer[i * 3] = r;
+            buffer[i * 3 + 1] = g;
+            buffer[i * 3 + 2] = b;
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::with_emphasis(&SYSTEM_PALETTE)
+    }
+}
+
+// 強調ビット (bit0: 赤, bit1: 緑, bit2: 青) が立っている色以外の成分を暗くする
+fn emphasize(index: usize, (r, g, b): (u8, u8, u8), emphasis: u8) -> (u8, u8, u8) {
+    // $xE, $xF の黒は強調の影響を受けない
+    if index & 0x0F >= 0x0E {
+        return (r, g, b);
+    }
+
+    let attenuate = |value: u8, emphasized: bool| {
+        if emphasis != 0 && !emphasized {
+            (value as f32 * EMPHASIS_ATTENUATION).round() as u8
+        } else {
+            value
+        }
+    };
+
+    // 複数の色が強調されると、どれにも含まれない成分はその分だけ暗くなる
+    let mut rgb = (r, g, b);
+    for bit in 0..3 {
+        if emphasis & (1 << bit) == 0 {
+            continue;
+        }
+        rgb = (
+            attenuate(rgb.0, bit == 0),
+            attenuate(rgb.1, bit == 1),
+            attenuate(rgb.2, bit == 2),
+        );
+    }
+    rgb
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_palette() {
+        let palette = Palette::default();
+        assert_eq!(palette.rgb(0x00), (0x80, 0x80, 0x80));
+        assert_eq!(palette.rgb(0x30), (0xFF, 0xFF, 0xFF));
+    }
+
+    #[test]
+    fn test_emphasis_darkens_other_channels() {
+        let palette = Palette::default();
+        // 赤を強調した白
+        let (r, g, b) = palette.rgb(0x30 | (0b001 << 6));
+        assert_eq!(r, 0xFF);
+        assert!(g < 0xFF);
+        assert!(b < 0xFF);
+
+        // 全部強調すると全体が暗くなる
+        let (r, g, b) = palette.rgb(0x30 | (0b111 << 6));
+        assert!(r < 0xFF && g < 0xFF && b < 0xFF);
+    }
+
+    #[test]
+    fn test_emphasis_does_not_affect_black() {
+        let palette = Palette::default();
+        assert_eq!(palette.rgb(0x0F | (0b111 << 6)), palette.rgb(0x0F));
+    }
+
+    #[test]
+    fn test_from_pal_64_colors() {
+        let data: Vec<u8> = (0..PALETTE_SIZE * 3).map(|i| i as u8).collect();
+        let palette = Palette::from_pal(&data).unwrap();
+        assert_eq!(palette.rgb(0x01), (3, 4, 5));
+        assert_eq!(palette.rgb(0x01 | (0b010 << 6)).1, 4);
+    }
+
+    #[test]
+    fn test_from_pal_512_colors() {
+        let mut data = vec![0; EMPHASIS_PALETTE_SIZE * 3];
+        data[(0x41) * 3] = 0xAB;
+        let palette = Palette::from_pal(&data).unwrap();
+        assert_eq!(palette.rgb(0x41), (0xAB, 0, 0));
+    }
+
+    #[test]
+    fn test_from_pal_invalid_size() {
+        assert!(Palette::from_pal(&[0; 10]).is_err());
+    }
+
+    #[test]
+    fn test_write_rgb() {
+        let mut frame = Frame::new();
+        frame.set_pixel(1, 0, 0x30);
+        let mut buffer = vec![0; WIDTH * HEIGHT * 3];
+        Palette::default().write_rgb(&frame, &mut buffer);
+        assert_eq!(&buffer[0..6], &[0x80, 0x80, 0x80, 0xFF, 0xFF, 0xFF]);
+    }
+}
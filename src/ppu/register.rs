@@ -1,3 +1,7 @@
-pub mod addr;
 pub mod constans;
 pub mod control;
+pub mod data;
+pub mod loopy;
+pub mod mask;
+pub mod oam;
+pub mod status;
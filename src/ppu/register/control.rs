@@ -45,6 +45,34 @@ impl ControlRegister {
         }
     }
 
+    pub fn sprt_pattern_addr(&self) -> u16 {
+        if !self.contains(ControlRegister::SPRITE_PATTERN_ADDR) {
+            0x0000
+        } else {
+            0x1000
+        }
+    }
+
+    pub fn sprite_size(&self) -> u8 {
+        if !self.contains(ControlRegister::SPRITE_SIZE) {
+            8
+        } else {
+            16
+        }
+    }
+
+    pub fn bknd_pattern_addr(&self) -> u16 {
+        if !self.contains(ControlRegister::BACKGROUD_PATTERN_ADDR) {
+            0x0000
+        } else {
+            0x1000
+        }
+    }
+
+    pub fn generate_vblank_nmi(&self) -> bool {
+        self.contains(ControlRegister::GENERATE_NMI)
+    }
+
     pub fn update(&mut self, data: u8) {
         *self = ControlRegister::from_bits_truncate(data);
     }
@@ -63,6 +91,33 @@ mod tests {
         assert_eq!(control.vram_addr_increment(), 1);
     }
 
+    #[test]
+    fn test_sprt_pattern_addr() {
+        let control = ControlRegister::from_bits_truncate(0b0000_1000);
+        assert_eq!(control.sprt_pattern_addr(), 0x1000);
+
+        let control = ControlRegister::from_bits_truncate(0b0000_0000);
+        assert_eq!(control.sprt_pattern_addr(), 0x0000);
+    }
+
+    #[test]
+    fn test_sprite_size() {
+        let control = ControlRegister::from_bits_truncate(0b0010_0000);
+        assert_eq!(control.sprite_size(), 16);
+
+        let control = ControlRegister::from_bits_truncate(0b0000_0000);
+        assert_eq!(control.sprite_size(), 8);
+    }
+
+    #[test]
+    fn test_bknd_pattern_addr() {
+        let control = ControlRegister::from_bits_truncate(0b0001_0000);
+        assert_eq!(control.bknd_pattern_addr(), 0x1000);
+
+        let control = ControlRegister::from_bits_truncate(0b0000_0000);
+        assert_eq!(control.bknd_pattern_addr(), 0x0000);
+    }
+
     #[test]
     fn test_update() {
         let mut control = ControlRegister::new();
@@ -0,0 +1,54 @@
+// PPUDATA ($2007) の読み込みバッファ
+//
+// $0000-$3EFF の読み込みは1回遅れて結果が返る。パレット($3F00-$3FFF)は即座に返るが、
+// バッファにはその下にあるネームテーブルの値が入る
+#[derive(Debug, Default, PartialEq)]
+pub struct DataRegister {
+    pub read_buffer: u8,
+}
+
+impl DataRegister {
+    pub fn new() -> Self {
+        DataRegister { read_buffer: 0 }
+    }
+
+    // 前回読み込んだ値を返し、今回の値をバッファに入れる
+    pub fn read_buffered(&mut self, data: u8) -> u8 {
+        let result = self.read_buffer;
+        self.read_buffer = data;
+        result
+    }
+
+    // パレットの値をそのまま返し、下にあるネームテーブルの値をバッファに入れる
+    pub fn read_palette(&mut self, palette: u8, underlying: u8) -> u8 {
+        self.read_buffer = underlying;
+        palette
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        let data_reg = DataRegister::new();
+        assert_eq!(data_reg.read_buffer, 0);
+    }
+
+    #[test]
+    fn test_read_buffered() {
+        let mut data_reg = DataRegister::new();
+        assert_eq!(data_reg.read_buffered(0x12), 0);
+        assert_eq!(data_reg.read_buffered(0x34), 0x12);
+        assert_eq!(data_reg.read_buffer, 0x34);
+    }
+
+    #[test]
+    fn test_read_palette() {
+        let mut data_reg = DataRegister::new();
+        data_reg.read_buffered(0x12);
+        assert_eq!(data_reg.read_palette(0x0F, 0x56), 0x0F);
+        assert_eq!(data_reg.read_buffer, 0x56);
+    }
+}
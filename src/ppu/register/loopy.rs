@@ -0,0 +1,280 @@
+// PPU内部のスクロール/アドレスレジスタ (通称 Loopy レジスタ)
+//
+// v, t のビット配置
+//    yyy NN YYYYY XXXXX
+//    ||| || ||||| +++++-- coarse X scroll
+//    ||| || +++++-------- coarse Y scroll
+//    ||| ++-------------- nametable select
+//    +++----------------- fine Y scroll
+//
+// v: 現在のVRAMアドレス (15bit)
+// t: 一時VRAMアドレス。画面左上のタイルのアドレスとして使われる (15bit)
+// x: fine X scroll (3bit)
+// w: PPUSCROLLとPPUADDRで共有される書き込みラッチ (false: 1回目, true: 2回目)
+
+const COARSE_X: u16 = 0x001F;
+const COARSE_Y: u16 = 0x03E0;
+const NAME_TABLE_X: u16 = 0x0400;
+const NAME_TABLE_Y: u16 = 0x0800;
+const NAME_TABLE: u16 = NAME_TABLE_X | NAME_TABLE_Y;
+const FINE_Y: u16 = 0x7000;
+
+// 水平方向・垂直方向に関するビット
+const HORIZONTAL_BITS: u16 = COARSE_X | NAME_TABLE_X;
+const VERTICAL_BITS: u16 = COARSE_Y | NAME_TABLE_Y | FINE_Y;
+
+const VRAM_ADDR_MASK: u16 = 0x3FFF;
+const REGISTER_MASK: u16 = 0x7FFF;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct LoopyRegister {
+    pub v: u16,
+    pub t: u16,
+    pub x: u8,
+    pub w: bool,
+}
+
+impl LoopyRegister {
+    pub fn new() -> Self {
+        LoopyRegister {
+            v: 0,
+            t: 0,
+            x: 0,
+            w: false,
+        }
+    }
+
+    // $2000 write: t: ...GH.. ........ <- d: ......GH
+    pub fn write_ctrl(&mut self, data: u8) {
+        self.t = (self.t & !NAME_TABLE) | (((data & 0b11) as u16) << 10);
+    }
+
+    // $2005 first write:  t: ....... ...ABCDE <- d: ABCDE...
+    //                     x:              FGH <- d: .....FGH
+    // $2005 second write: t: FGH..AB CDE..... <- d: ABCDEFGH
+    pub fn write_scroll(&mut self, data: u8) {
+        if !self.w {
+            self.t = (self.t & !COARSE_X) | (data >> 3) as u16;
+            self.x = data & 0b111;
+        } else {
+            self.t = (self.t & !(COARSE_Y | FINE_Y))
+                | (((data & 0b111) as u16) << 12)
+                | (((data >> 3) as u16) << 5);
+        }
+        self.w = !self.w;
+    }
+
+    // $2006 first write:  t: .CDEFGH ........ <- d: ..CDEFGH
+    //                     (t の bit14 は 0 になる)
+    // $2006 second write: t: ....... ABCDEFGH <- d: ABCDEFGH
+    //                     v: <...all bits...> <- t: <...all bits...>
+    pub fn write_addr(&mut self, data: u8) {
+        if !self.w {
+            self.t = (self.t & 0x00FF) | (((data & 0b0011_1111) as u16) << 8);
+        } else {
+            self.t = (self.t & 0xFF00) | data as u16;
+            self.v = self.t;
+        }
+        self.w = !self.w;
+    }
+
+    // $2002 read: w <- 0
+    pub fn reset_latch(&mut self) {
+        self.w = false;
+    }
+
+    // PPUDATAのアクセスで使われるアドレス
+    pub fn vram_addr(&self) -> u16 {
+        self.v & VRAM_ADDR_MASK
+    }
+
+    // レンダリング外でのPPUDATAアクセス後のインクリメント
+    pub fn increment(&mut self, inc: u8) {
+        self.v = self.v.wrapping_add(inc as u16) & REGISTER_MASK;
+    }
+
+    pub fn coarse_x(&self) -> u16 {
+        self.v & COARSE_X
+    }
+
+    pub fn coarse_y(&self) -> u16 {
+        (self.v & COARSE_Y) >> 5
+    }
+
+    pub fn fine_y(&self) -> u16 {
+        (self.v & FINE_Y) >> 12
+    }
+
+    pub fn name_table(&self) -> u16 {
+        (self.v & NAME_TABLE) >> 10
+    }
+
+    // 8ドットごとに次のタイルへ進む。右端を越えたら水平方向のネームテーブルを切り替える
+    pub fn increment_coarse_x(&mut self) {
+        if self.v & COARSE_X == 31 {
+            self.v &= !COARSE_X;
+            self.v ^= NAME_TABLE_X;
+        } else {
+            self.v += 1;
+        }
+    }
+
+    // ドット256で次のラインへ進む。29行目で折り返して垂直方向のネームテーブルを切り替える
+    // (31行目を越えた場合は属性テーブル領域を読んだ後、ネームテーブルを切り替えずに0へ戻る)
+    pub fn increment_y(&mut self) {
+        if self.v & FINE_Y != FINE_Y {
+            self.v += 1 << 12;
+            return;
+        }
+
+        self.v &= !FINE_Y;
+        let mut coarse_y = self.coarse_y();
+        if coarse_y == 29 {
+            coarse_y = 0;
+            self.v ^= NAME_TABLE_Y;
+        } else if coarse_y == 31 {
+            coarse_y = 0;
+        } else {
+            coarse_y += 1;
+        }
+        self.v = (self.v & !COARSE_Y) | (coarse_y << 5);
+    }
+
+    // ドット257: v: ....A.. ...BCDEF <- t: ....A.. ...BCDEF
+    pub fn copy_horizontal(&mut self) {
+        self.v = (self.v & !HORIZONTAL_BITS) | (self.t & HORIZONTAL_BITS);
+    }
+
+    // プリレンダーラインのドット280-304: v: GHIA.BC DEF..... <- t: GHIA.BC DEF.....
+    pub fn copy_vertical(&mut self) {
+        self.v = (self.v & !VERTICAL_BITS) | (self.t & VERTICAL_BITS);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        let loopy = LoopyRegister::new();
+        assert_eq!(loopy.v, 0);
+        assert_eq!(loopy.t, 0);
+        assert_eq!(loopy.x, 0);
+        assert!(!loopy.w);
+    }
+
+    #[test]
+    fn test_write_ctrl() {
+        let mut loopy = LoopyRegister::new();
+        loopy.write_ctrl(0b0000_0011);
+        assert_eq!(loopy.t, NAME_TABLE);
+        loopy.write_ctrl(0b0000_0001);
+        assert_eq!(loopy.t, NAME_TABLE_X);
+    }
+
+    #[test]
+    fn test_write_scroll() {
+        let mut loopy = LoopyRegister::new();
+        loopy.write_scroll(0b0111_1101);
+        // coarse X = 15
+        assert_eq!(loopy.t, 0x000F);
+        assert_eq!(loopy.x, 0b101);
+        assert!(loopy.w);
+
+        loopy.write_scroll(0b0101_1110);
+        // fine Y = 6, coarse Y = 11, coarse X = 15
+        assert_eq!(loopy.t, 0x616F);
+        assert!(!loopy.w);
+    }
+
+    #[test]
+    fn test_write_addr() {
+        let mut loopy = LoopyRegister::new();
+        loopy.write_addr(0x7F);
+        assert_eq!(loopy.t, 0x3F00);
+        assert_eq!(loopy.v, 0);
+
+        loopy.write_addr(0x12);
+        assert_eq!(loopy.t, 0x3F12);
+        assert_eq!(loopy.v, 0x3F12);
+        assert!(!loopy.w);
+    }
+
+    #[test]
+    fn test_scroll_and_addr_share_latch() {
+        // $2006, $2005, $2005, $2006 で途中のスクロール位置を変える手法
+        let mut loopy = LoopyRegister::new();
+        loopy.write_addr(0x04);
+        loopy.write_scroll(0x3E);
+        loopy.write_scroll(0x7D);
+        loopy.write_addr(0xEF);
+        assert_eq!(loopy.v, 0x64EF);
+        assert_eq!(loopy.x, 0b101);
+    }
+
+    #[test]
+    fn test_reset_latch() {
+        let mut loopy = LoopyRegister::new();
+        loopy.write_addr(0x21);
+        loopy.reset_latch();
+        assert!(!loopy.w);
+    }
+
+    #[test]
+    fn test_increment() {
+        let mut loopy = LoopyRegister::new();
+        loopy.v = 0x23FF;
+        loopy.increment(1);
+        assert_eq!(loopy.vram_addr(), 0x2400);
+        loopy.increment(32);
+        assert_eq!(loopy.vram_addr(), 0x2420);
+
+        loopy.v = 0x7FFF;
+        loopy.increment(1);
+        assert_eq!(loopy.vram_addr(), 0x0000);
+    }
+
+    #[test]
+    fn test_increment_coarse_x() {
+        let mut loopy = LoopyRegister::new();
+        loopy.v = 30;
+        loopy.increment_coarse_x();
+        assert_eq!(loopy.coarse_x(), 31);
+        loopy.increment_coarse_x();
+        assert_eq!(loopy.coarse_x(), 0);
+        assert_eq!(loopy.name_table(), 0b01);
+    }
+
+    #[test]
+    fn test_increment_y() {
+        let mut loopy = LoopyRegister::new();
+        loopy.increment_y();
+        assert_eq!(loopy.fine_y(), 1);
+
+        loopy.v = FINE_Y | (28 << 5);
+        loopy.increment_y();
+        assert_eq!(loopy.fine_y(), 0);
+        assert_eq!(loopy.coarse_y(), 29);
+
+        loopy.v = FINE_Y | (29 << 5);
+        loopy.increment_y();
+        assert_eq!(loopy.coarse_y(), 0);
+        assert_eq!(loopy.name_table(), 0b10);
+
+        loopy.v = FINE_Y | (31 << 5);
+        loopy.increment_y();
+        assert_eq!(loopy.coarse_y(), 0);
+        assert_eq!(loopy.name_table(), 0b00);
+    }
+
+    #[test]
+    fn test_copy_horizontal_and_vertical() {
+        let mut loopy = LoopyRegister::new();
+        loopy.t = 0x7FFF;
+        loopy.copy_horizontal();
+        assert_eq!(loopy.v, HORIZONTAL_BITS);
+        loopy.copy_vertical();
+        assert_eq!(loopy.v, 0x7FFF);
+    }
+}
@@ -0,0 +1,90 @@
+use bitflags::bitflags;
+
+bitflags! {
+
+//    7  bit  0
+//    ---- ----
+//    BGRs bMmG
+//    |||| ||||
+//    |||| |||+- Greyscale (0: normal color, 1: produce a greyscale display)
+//    |||| ||+-- 1: Show background in leftmost 8 pixels of screen, 0: Hide
+//    |||| |+--- 1: Show sprites in leftmost 8 pixels of screen, 0: Hide
+//    |||| +---- 1: Show background
+//    |||+------ 1: Show sprites
+//    ||+------- Emphasize red (green on PAL/Dendy)
+//    |+-------- Emphasize green (red on PAL/Dendy)
+//    +--------- Emphasize blue
+
+    #[derive(Debug, Default, PartialEq)]
+    pub struct MaskRegister: u8 {
+        const GREYSCALE                = 1;
+        const LEFTMOST_8PXL_BACKGROUND = 1 << 1;
+        const LEFTMOST_8PXL_SPRITE     = 1 << 2;
+        const SHOW_BACKGROUND          = 1 << 3;
+        const SHOW_SPRITES             = 1 << 4;
+        const EMPHASISE_RED            = 1 << 5;
+        const EMPHASISE_GREEN          = 1 << 6;
+        const EMPHASISE_BLUE           = 1 << 7;
+    }
+}
+
+impl MaskRegister {
+    pub fn new() -> Self {
+        MaskRegister::from_bits_truncate(0)
+    }
+
+    pub fn is_greyscale(&self) -> bool {
+        self.contains(MaskRegister::GREYSCALE)
+    }
+
+    pub fn leftmost_8pxl_background(&self) -> bool {
+        self.contains(MaskRegister::LEFTMOST_8PXL_BACKGROUND)
+    }
+
+    pub fn leftmost_8pxl_sprite(&self) -> bool {
+        self.contains(MaskRegister::LEFTMOST_8PXL_SPRITE)
+    }
+
+    pub fn show_background(&self) -> bool {
+        self.contains(MaskRegister::SHOW_BACKGROUND)
+    }
+
+    pub fn show_sprites(&self) -> bool {
+        self.contains(MaskRegister::SHOW_SPRITES)
+    }
+
+    pub fn is_rendering_enabled(&self) -> bool {
+        self.show_background() || self.show_sprites()
+    }
+
+    // 上位3ビット(赤・緑・青の強調)を0-7の値として返す
+    pub fn emphasis(&self) -> u8 {
+        self.bits() >> 5
+    }
+
+    pub fn update(&mut self, data: u8) {
+        *self = MaskRegister::from_bits_truncate(data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update() {
+        let mut mask = MaskRegister::new();
+        mask.update(0b0001_1110);
+        assert!(mask.leftmost_8pxl_background());
+        assert!(mask.leftmost_8pxl_sprite());
+        assert!(mask.show_background());
+        assert!(mask.show_sprites());
+        assert!(!mask.is_greyscale());
+    }
+
+    #[test]
+    fn test_emphasis() {
+        let mask = MaskRegister::from_bits_truncate(0b1010_0000);
+        assert_eq!(mask.emphasis(), 0b101);
+    }
+}
@@ -0,0 +1,76 @@
+const OAM_DATA_SIZE: usize = 256;
+// スプライト属性バイトの未使用ビット(2-4)は読むと0になる
+const ATTRIBUTE_UNUSED_BITS: u8 = 0b0001_1100;
+
+// OAMADDR ($2003) と OAMDATA ($2004)
+pub struct OamRegister {
+    pub addr: u8,
+    pub data: [u8; OAM_DATA_SIZE],
+}
+
+impl Default for OamRegister {
+    fn default() -> Self {
+        OamRegister::new()
+    }
+}
+
+impl OamRegister {
+    pub fn new() -> Self {
+        OamRegister {
+            addr: 0,
+            data: [0; OAM_DATA_SIZE],
+        }
+    }
+
+    pub fn write_addr(&mut self, value: u8) {
+        self.addr = value;
+    }
+
+    pub fn write_data(&mut self, value: u8) {
+        self.data[self.addr as usize] = value;
+        self.addr = self.addr.wrapping_add(1);
+    }
+
+    // 読み込みではOAMADDRは進まない
+    pub fn read_data(&self) -> u8 {
+        let value = self.data[self.addr as usize];
+        if self.addr % 4 == 2 {
+            value & !ATTRIBUTE_UNUSED_BITS
+        } else {
+            value
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_data_increments_addr() {
+        let mut oam = OamRegister::new();
+        oam.write_addr(0xFF);
+        oam.write_data(0x12);
+        oam.write_data(0x34);
+        assert_eq!(oam.data[0xFF], 0x12);
+        assert_eq!(oam.data[0x00], 0x34);
+        assert_eq!(oam.addr, 0x01);
+    }
+
+    #[test]
+    fn test_read_data_does_not_increment_addr() {
+        let mut oam = OamRegister::new();
+        oam.data[0x10] = 0x42;
+        oam.write_addr(0x10);
+        assert_eq!(oam.read_data(), 0x42);
+        assert_eq!(oam.addr, 0x10);
+    }
+
+    #[test]
+    fn test_read_attribute_byte_masks_unused_bits() {
+        let mut oam = OamRegister::new();
+        oam.data[0x02] = 0xFF;
+        oam.write_addr(0x02);
+        assert_eq!(oam.read_data(), 0xE3);
+    }
+}
@@ -0,0 +1,88 @@
+use bitflags::bitflags;
+
+bitflags! {
+
+//    7  bit  0
+//    ---- ----
+//    VSO. ....
+//    |||| ||||
+//    |||+-++++- PPU open bus. Returns stale PPU bus contents.
+//    ||+------- Sprite overflow. The intent was for this flag to be set
+//    ||         whenever more than eight sprites appear on a scanline, but a
+//    ||         hardware bug causes the actual behavior to be more complicated
+//    ||         and generate false positives as well as false negatives
+//    |+-------- Sprite 0 Hit.  Set when a nonzero pixel of sprite 0 overlaps
+//    |          a nonzero background pixel; cleared at dot 1 of the pre-render
+//    |          line.  Used for raster timing.
+//    +--------- Vertical blank has started (0: not in vblank; 1: in vblank).
+//               Set at dot 1 of line 241 (the line *after* the post-render
+//               line); cleared after reading $2002 and at dot 1 of the
+//               pre-render line.
+
+    #[derive(Debug, Default, PartialEq)]
+    pub struct StatusRegister: u8 {
+        const NOTUSED          = 1;
+        const NOTUSED2         = 1 << 1;
+        const NOTUSED3         = 1 << 2;
+        const NOTUSED4         = 1 << 3;
+        const NOTUSED5         = 1 << 4;
+        const SPRITE_OVERFLOW  = 1 << 5;
+        const SPRITE_ZERO_HIT  = 1 << 6;
+        const VBLANK_STARTED   = 1 << 7;
+    }
+}
+
+// 下位5ビットはオープンバス
+pub const OPEN_BUS_MASK: u8 = 0b0001_1111;
+
+impl StatusRegister {
+    pub fn new() -> Self {
+        StatusRegister::from_bits_truncate(0)
+    }
+
+    pub fn set_vblank_status(&mut self, status: bool) {
+        self.set(StatusRegister::VBLANK_STARTED, status);
+    }
+
+    pub fn set_sprite_zero_hit(&mut self, status: bool) {
+        self.set(StatusRegister::SPRITE_ZERO_HIT, status);
+    }
+
+    pub fn set_sprite_overflow(&mut self, status: bool) {
+        self.set(StatusRegister::SPRITE_OVERFLOW, status);
+    }
+
+    pub fn reset_vblank_status(&mut self) {
+        self.remove(StatusRegister::VBLANK_STARTED);
+    }
+
+    pub fn is_in_vblank(&self) -> bool {
+        self.contains(StatusRegister::VBLANK_STARTED)
+    }
+
+    pub fn snapshot(&self) -> u8 {
+        self.bits() & !OPEN_BUS_MASK
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_vblank_status() {
+        let mut status = StatusRegister::new();
+        status.set_vblank_status(true);
+        assert!(status.is_in_vblank());
+        status.reset_vblank_status();
+        assert!(!status.is_in_vblank());
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut status = StatusRegister::new();
+        status.set_sprite_zero_hit(true);
+        status.set_sprite_overflow(true);
+        assert_eq!(status.snapshot(), 0b0110_0000);
+    }
+}
@@ -0,0 +1,332 @@
+use super::frame::WIDTH;
+use super::register::loopy::LoopyRegister;
+use super::register::status::StatusRegister;
+use super::NesPPU;
+
+const TILE_SIZE: usize = 8;
+// 1ライン分 + fine X でずれる分のタイル数
+const TILES_PER_LINE: usize = WIDTH / TILE_SIZE + 1;
+const ATTRIBUTE_TABLE_OFFSET: u16 = 0x03C0;
+const BACKGROUND_PALETTE_START: usize = 0x00;
+const SPRITE_PALETTE_START: usize = 0x10;
+
+impl NesPPU {
+    // 1ライン分の背景とスプライトを描画する。スクロール位置はライン開始時点の v を使う
+    pub(super) fn render_scanline(&mut self, y: usize) {
+        let backdrop = self.palette_table[BACKGROUND_PALETTE_START];
+        let mut background = [0u8; WIDTH];
+        let mut sprites = [None; WIDTH];
+
+        if self.mask.show_background() {
+            self.fetch_background_line(&mut background);
+            if !self.mask.leftmost_8pxl_background() {
+                background[..TILE_SIZE].fill(0);
+            }
+        }
+
+        if self.mask.show_sprites() && y > 0 {
+            self.fetch_sprite_line(y, &mut sprites);
+            if !self.mask.leftmost_8pxl_sprite() {
+                sprites[..TILE_SIZE].fill(None);
+            }
+        }
+
+        // グレースケールではカラー番号の下位4ビットが落ちる
+        let color_mask = if self.mask.is_greyscale() { 0x30 } else { 0x3F };
+        let emphasis = (self.mask.emphasis() as u16) << 6;
+
+        self.sprite_zero_hit_dot = None;
+        for x in 0..WIDTH {
+            let background_opaque = background[x] & 0b11 != 0;
+
+            let color = match sprites[x] {
+                Some(sprite) if !background_opaque || !sprite.behind_background => {
+                    self.palette_table[SPRITE_PALETTE_START + sprite.pixel as usize]
+                }
+                _ if background_opaque => {
+                    self.palette_table[BACKGROUND_PALETTE_START + background[x] as usize]
+                }
+                _ => backdrop,
+            };
+            self.frame
+                .set_pixel(x, y, (color & color_mask) as u16 | emphasis);
+
+            // 右端のピクセルではスプライト0ヒットは起きない
+            let sprite_zero = sprites[x].is_some_and(|sprite| sprite.sprite_zero);
+            if sprite_zero
+                && background_opaque
+                && x != WIDTH - 1
+                && self.sprite_zero_hit_dot.is_none()
+                && !self.status.contains(StatusRegister::SPRITE_ZERO_HIT)
+            {
+                // ピクセル x はドット x + 1 で出力される
+                self.sprite_zero_hit_dot = Some(x as u16 + 1);
+            }
+        }
+    }
+
+    // ラインの各ピクセルについて (パレット番号 << 2) | ピクセル値 を求める
+    fn fetch_background_line(&self, line: &mut [u8; WIDTH]) {
+        let mut scroll = LoopyRegister::new();
+        scroll.v = self.line_start_v;
+        let fine_x = self.loopy.x as usize;
+        let pattern_base = self.ctrl.bknd_pattern_addr();
+
+        for tile_index in 0..TILES_PER_LINE {
+            let name_table_addr = 0x2000 | (scroll.v & 0x0FFF);
+            let tile = self.read_vram(name_table_addr) as u16;
+
+            // 属性テーブルは 4x4 タイルごとに1バイト、2x2 タイルごとに2ビット
+            let attribute_addr = 0x2000
+                | ATTRIBUTE_TABLE_OFFSET
+                | (scroll.name_table() << 10)
+                | ((scroll.coarse_y() >> 2) << 3)
+                | (scroll.coarse_x() >> 2);
+            let attribute = self.read_vram(attribute_addr);
+            let shift = ((scroll.coarse_y() & 0b10) << 1) | (scroll.coarse_x() & 0b10);
+            let palette = (attribute >> shift) & 0b11;
+
+            let pattern_addr = pattern_base + tile * 16 + scroll.fine_y();
+            let lower = self.read_vram(pattern_addr);
+            let upper = self.read_vram(pattern_addr + 8);
+
+            for pixel_x in 0..TILE_SIZE {
+                let x = (tile_index * TILE_SIZE + pixel_x).wrapping_sub(fine_x);
+                if x >= WIDTH {
+                    continue;
+                }
+                let bit = 7 - pixel_x;
+                let pixel = (((upper >> bit) & 1) << 1) | ((lower >> bit) & 1);
+                if pixel != 0 {
+                    line[x] = (palette << 2) | pixel;
+                }
+            }
+
+            scroll.increment_coarse_x();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ppu::{
+        DOTS_PER_SCANLINE, PRE_RENDER_SCANLINE, SCANLINES_PER_FRAME, VBLANK_SCANLINE,
+    };
+    use crate::rom::Mirroring;
+
+    const BACKDROP: u8 = 0x0F;
+
+    // タイル1: 全ピクセルが1、タイル2: 全ピクセルが3、タイル3: 左端の列だけ1
+    fn test_ppu() -> NesPPU {
+        let mut chr_rom = vec![0; 0x2000];
+        chr_rom[16..24].fill(0xFF);
+        chr_rom[32..48].fill(0xFF);
+        chr_rom[48..56].fill(0x80);
+
+        let mut ppu = NesPPU::new(chr_rom, Mirroring::VERTICAL);
+        ppu.palette_table[0x00] = BACKDROP;
+        ppu.palette_table[0x01] = 0x16;
+        ppu.palette_table[0x03] = 0x2A;
+        ppu.palette_table[0x05] = 0x12;
+        ppu.write_to_mask(0b0000_1010);
+        ppu
+    }
+
+    #[test]
+    fn test_render_background_tile() {
+        let mut ppu = test_ppu();
+        ppu.vram[0x0000] = 1;
+        ppu.vram[0x0001] = 2;
+        ppu.render_scanline(0);
+
+        assert_eq!(ppu.frame.get_pixel(0, 0), 0x16);
+        assert_eq!(ppu.frame.get_pixel(7, 0), 0x16);
+        assert_eq!(ppu.frame.get_pixel(8, 0), 0x2A);
+        assert_eq!(ppu.frame.get_pixel(16, 0), BACKDROP as u16);
+    }
+
+    #[test]
+    fn test_render_background_attribute_palette() {
+        let mut ppu = test_ppu();
+        // 左上 4x4 タイルのうち右上の 2x2 にパレット1を使う
+        ppu.vram[0x03C0] = 0b0000_0100;
+        ppu.vram[0x0002] = 1;
+        ppu.vram[0x0000] = 1;
+        ppu.render_scanline(0);
+
+        assert_eq!(ppu.frame.get_pixel(0, 0), 0x16);
+        assert_eq!(ppu.frame.get_pixel(16, 0), 0x12);
+    }
+
+    #[test]
+    fn test_render_background_fine_x_scroll() {
+        let mut ppu = test_ppu();
+        ppu.vram[0x0001] = 3;
+        ppu.loopy.x = 3;
+        ppu.render_scanline(0);
+
+        assert_eq!(ppu.frame.get_pixel(4, 0), BACKDROP as u16);
+        assert_eq!(ppu.frame.get_pixel(5, 0), 0x16);
+        assert_eq!(ppu.frame.get_pixel(6, 0), BACKDROP as u16);
+    }
+
+    #[test]
+    fn test_render_background_crosses_name_table() {
+        let mut ppu = test_ppu();
+        ppu.vram[0x0400] = 1; // ネームテーブル1の左端
+        ppu.line_start_v = 31;
+        ppu.render_scanline(0);
+
+        assert_eq!(ppu.frame.get_pixel(7, 0), BACKDROP as u16);
+        assert_eq!(ppu.frame.get_pixel(8, 0), 0x16);
+    }
+
+    #[test]
+    fn test_render_background_left_column_clipping() {
+        let mut ppu = test_ppu();
+        ppu.vram[0x0000] = 1;
+        ppu.vram[0x0001] = 1;
+        ppu.write_to_mask(0b0000_1000);
+        ppu.render_scanline(0);
+
+        assert_eq!(ppu.frame.get_pixel(0, 0), BACKDROP as u16);
+        assert_eq!(ppu.frame.get_pixel(8, 0), 0x16);
+    }
+
+    #[test]
+    fn test_render_greyscale_and_emphasis() {
+        let mut ppu = test_ppu();
+        ppu.vram[0x0000] = 1;
+        ppu.write_to_mask(0b1010_1011);
+        ppu.render_scanline(0);
+
+        assert_eq!(ppu.frame.get_pixel(0, 0), 0x10 | (0b101 << 6));
+    }
+
+    #[test]
+    fn test_render_background_disabled_draws_backdrop() {
+        let mut ppu = test_ppu();
+        ppu.vram[0x0000] = 1;
+        ppu.write_to_mask(0);
+        ppu.render_scanline(0);
+
+        assert_eq!(ppu.frame.get_pixel(0, 0), BACKDROP as u16);
+    }
+
+    #[test]
+    fn test_render_frame_with_vertical_scroll() {
+        let mut ppu = test_ppu();
+        // 2行目のタイル (y = 8..15) を画面の一番上に持ってくる
+        ppu.vram[32] = 2;
+        ppu.write_to_scroll(0);
+        ppu.write_to_scroll(8);
+
+        // プリレンダーラインから1フレーム分進める
+        ppu.scanline = PRE_RENDER_SCANLINE;
+        ppu.tick(DOTS_PER_SCANLINE as usize * SCANLINES_PER_FRAME as usize);
+
+        assert_eq!(ppu.frame.get_pixel(0, 0), 0x2A);
+        assert_eq!(ppu.frame.get_pixel(0, 7), 0x2A);
+        assert_eq!(ppu.frame.get_pixel(0, 8), BACKDROP as u16);
+    }
+
+    fn set_sprite(ppu: &mut NesPPU, index: usize, y: u8, tile: u8, attribute: u8, x: u8) {
+        ppu.oam.data[index * 4..index * 4 + 4].copy_from_slice(&[y, tile, attribute, x]);
+    }
+
+    fn sprite_test_ppu() -> NesPPU {
+        let mut ppu = test_ppu();
+        ppu.oam.data.fill(0xFF);
+        ppu.palette_table[0x11] = 0x30;
+        ppu.write_to_mask(0b0001_1110);
+        ppu
+    }
+
+    #[test]
+    fn test_render_sprite_in_front_of_background() {
+        let mut ppu = sprite_test_ppu();
+        ppu.vram[0x0002] = 1;
+        set_sprite(&mut ppu, 0, 0, 1, 0, 16);
+        set_sprite(&mut ppu, 1, 0, 1, 0, 40);
+
+        ppu.evaluate_sprites(0);
+        ppu.render_scanline(1);
+        assert_eq!(ppu.frame.get_pixel(16, 1), 0x30);
+        assert_eq!(ppu.frame.get_pixel(40, 1), 0x30);
+        assert_eq!(ppu.frame.get_pixel(24, 1), BACKDROP as u16);
+    }
+
+    #[test]
+    fn test_render_sprite_behind_background() {
+        let mut ppu = sprite_test_ppu();
+        ppu.vram[0x0002] = 1;
+        set_sprite(&mut ppu, 0, 0, 1, 0b0010_0000, 12);
+
+        ppu.evaluate_sprites(0);
+        ppu.render_scanline(1);
+        // 背景が透明な部分だけスプライトが見える
+        assert_eq!(ppu.frame.get_pixel(15, 1), 0x30);
+        assert_eq!(ppu.frame.get_pixel(16, 1), 0x16);
+    }
+
+    #[test]
+    fn test_render_sprite_behind_background_hides_later_sprites() {
+        let mut ppu = sprite_test_ppu();
+        ppu.vram[0x0002] = 1;
+        ppu.palette_table[0x15] = 0x21;
+        set_sprite(&mut ppu, 0, 0, 1, 0b0010_0000, 16);
+        set_sprite(&mut ppu, 1, 0, 1, 0b0000_0001, 16);
+
+        ppu.evaluate_sprites(0);
+        ppu.render_scanline(1);
+        assert_eq!(ppu.frame.get_pixel(16, 1), 0x16);
+    }
+
+    #[test]
+    fn test_render_sprite_left_column_clipping() {
+        let mut ppu = sprite_test_ppu();
+        ppu.write_to_mask(0b0001_1010);
+        set_sprite(&mut ppu, 0, 0, 1, 0, 4);
+
+        ppu.evaluate_sprites(0);
+        ppu.render_scanline(1);
+        assert_eq!(ppu.frame.get_pixel(7, 1), BACKDROP as u16);
+        assert_eq!(ppu.frame.get_pixel(8, 1), 0x30);
+    }
+
+    fn tick_to(ppu: &mut NesPPU, scanline: u16, dot: u16) {
+        while ppu.scanline != scanline || ppu.dot != dot {
+            ppu.tick(1);
+        }
+    }
+
+    #[test]
+    fn test_sprite_zero_hit_timing() {
+        let mut ppu = sprite_test_ppu();
+        ppu.vram[0x0000] = 1;
+        set_sprite(&mut ppu, 0, 3, 1, 0, 4);
+        ppu.write_to_scroll(0);
+        ppu.write_to_scroll(0);
+        ppu.scanline = PRE_RENDER_SCANLINE;
+
+        // スプライト0はライン4のピクセル4 (ドット5) で背景と重なる
+        tick_to(&mut ppu, 4, 5);
+        assert!(!ppu.status.contains(StatusRegister::SPRITE_ZERO_HIT));
+        ppu.tick(1);
+        assert!(ppu.status.contains(StatusRegister::SPRITE_ZERO_HIT));
+
+        tick_to(&mut ppu, PRE_RENDER_SCANLINE, 2);
+        assert!(!ppu.status.contains(StatusRegister::SPRITE_ZERO_HIT));
+    }
+
+    #[test]
+    fn test_no_sprite_zero_hit_on_transparent_background() {
+        let mut ppu = sprite_test_ppu();
+        set_sprite(&mut ppu, 0, 3, 1, 0, 4);
+        ppu.scanline = PRE_RENDER_SCANLINE;
+
+        tick_to(&mut ppu, VBLANK_SCANLINE, 0);
+        assert!(!ppu.status.contains(StatusRegister::SPRITE_ZERO_HIT));
+    }
+}
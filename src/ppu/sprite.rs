@@ -0,0 +1,264 @@
+use super::frame::WIDTH;
+use super::NesPPU;
+
+pub const MAX_SPRITES_PER_LINE: usize = 8;
+const OAM_SPRITES: usize = 64;
+const OAM_ENTRY_SIZE: usize = 4;
+
+//    76543210
+//    ||||||||
+//    ||||||++- Palette (4 to 7) of sprite
+//    |||+++--- Unimplemented (read 0)
+//    ||+------ Priority (0: in front of background; 1: behind background)
+//    |+------- Flip sprite horizontally
+//    +-------- Flip sprite vertically
+const ATTRIBUTE_PALETTE: u8 = 0b0000_0011;
+const ATTRIBUTE_BEHIND_BACKGROUND: u8 = 0b0010_0000;
+const ATTRIBUTE_FLIP_HORIZONTAL: u8 = 0b0100_0000;
+const ATTRIBUTE_FLIP_VERTICAL: u8 = 0b1000_0000;
+
+// セカンダリOAMに積まれた、次のラインに表示するスプライト
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineSprite {
+    pub sprite_zero: bool,
+    pub y: u8,
+    pub tile: u8,
+    pub attribute: u8,
+    pub x: u8,
+}
+
+// スプライトの1ピクセル
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpritePixel {
+    // (パレット番号 << 2) | ピクセル値
+    pub pixel: u8,
+    pub behind_background: bool,
+    pub sprite_zero: bool,
+}
+
+impl NesPPU {
+    // ライン scanline の終わりに、次のラインに表示するスプライトを最大8個選ぶ
+    pub(super) fn evaluate_sprites(&mut self, scanline: u16) {
+        self.line_sprites.clear();
+        let height = self.ctrl.sprite_size() as u16;
+        let in_range = |y: u8| scanline.wrapping_sub(y as u16) < height;
+
+        let mut n = 0;
+        while n < OAM_SPRITES {
+            let entry = &self.oam.data[n * OAM_ENTRY_SIZE..(n + 1) * OAM_ENTRY_SIZE];
+            if in_range(entry[0]) {
+                self.line_sprites.push(LineSprite {
+                    sprite_zero: n == 0,
+                    y: entry[0],
+                    tile: entry[1],
+                    attribute: entry[2],
+                    x: entry[3],
+                });
+            }
+            n += 1;
+            if self.line_sprites.len() == MAX_SPRITES_PER_LINE {
+                break;
+            }
+        }
+
+        // 9個目以降の探索にはハードウェアのバグがあり、Y座標以外のバイトも
+        // Y座標として比較してしまう (n と一緒に m もインクリメントされる)
+        let mut m = 0;
+        while n < OAM_SPRITES {
+            let y = self.oam.data[n * OAM_ENTRY_SIZE + m];
+            if in_range(y) {
+                self.status.set_sprite_overflow(true);
+                break;
+            }
+            n += 1;
+            m = (m + 1) % OAM_ENTRY_SIZE;
+        }
+    }
+
+    // 選ばれたスプライトを1ライン分展開する。OAMの番号が小さいものが優先される
+    pub(super) fn fetch_sprite_line(&self, y: usize, line: &mut [Option<SpritePixel>; WIDTH]) {
+        let height = self.ctrl.sprite_size() as usize;
+
+        for sprite in &self.line_sprites {
+            // スプライトはOAMのY座標の1ライン下から表示される
+            let mut row = match (y - 1).checked_sub(sprite.y as usize) {
+                Some(row) if row < height => row,
+                _ => continue,
+            };
+            if sprite.attribute & ATTRIBUTE_FLIP_VERTICAL != 0 {
+                row = height - 1 - row;
+            }
+
+            let (bank, mut tile) = if height == 16 {
+                (((sprite.tile & 1) as u16) * 0x1000, sprite.tile & 0xFE)
+            } else {
+                (self.ctrl.sprt_pattern_addr(), sprite.tile)
+            };
+            if row >= 8 {
+                tile += 1;
+                row -= 8;
+            }
+
+            let pattern_addr = bank + tile as u16 * 16 + row as u16;
+            let lower = self.read_vram(pattern_addr);
+            let upper = self.read_vram(pattern_addr + 8);
+            let palette = sprite.attribute & ATTRIBUTE_PALETTE;
+
+            for pixel_x in 0..8 {
+                let x = sprite.x as usize + pixel_x;
+                if x >= WIDTH || line[x].is_some() {
+                    continue;
+                }
+
+                let bit = if sprite.attribute & ATTRIBUTE_FLIP_HORIZONTAL != 0 {
+                    pixel_x
+                } else {
+                    7 - pixel_x
+                };
+                let pixel = (((upper >> bit) & 1) << 1) | ((lower >> bit) & 1);
+                if pixel == 0 {
+                    continue;
+                }
+
+                line[x] = Some(SpritePixel {
+                    pixel: (palette << 2) | pixel,
+                    behind_background: sprite.attribute & ATTRIBUTE_BEHIND_BACKGROUND != 0,
+                    sprite_zero: sprite.sprite_zero,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ppu::register::status::StatusRegister;
+    use crate::rom::Mirroring;
+
+    // タイル1: 左上の1ピクセルだけ値1、タイル2: 全ピクセルが値2
+    fn test_ppu() -> NesPPU {
+        let mut chr_rom = vec![0; 0x2000];
+        chr_rom[16] = 0x80;
+        chr_rom[40..48].fill(0xFF);
+        let mut ppu = NesPPU::new(chr_rom, Mirroring::VERTICAL);
+        ppu.oam.data.fill(0xFF);
+        ppu
+    }
+
+    fn set_sprite(ppu: &mut NesPPU, index: usize, y: u8, tile: u8, attribute: u8, x: u8) {
+        let entry = index * OAM_ENTRY_SIZE;
+        ppu.oam.data[entry..entry + OAM_ENTRY_SIZE].copy_from_slice(&[y, tile, attribute, x]);
+    }
+
+    #[test]
+    fn test_evaluate_sprites_in_range() {
+        let mut ppu = test_ppu();
+        set_sprite(&mut ppu, 0, 10, 1, 0, 0);
+        set_sprite(&mut ppu, 1, 3, 1, 0, 0);
+        set_sprite(&mut ppu, 2, 2, 1, 0, 0);
+
+        ppu.evaluate_sprites(10);
+        assert_eq!(ppu.line_sprites.len(), 2);
+        assert!(ppu.line_sprites[0].sprite_zero);
+        assert_eq!(ppu.line_sprites[1].y, 3);
+    }
+
+    #[test]
+    fn test_evaluate_sprites_8x16() {
+        let mut ppu = test_ppu();
+        ppu.write_to_ctrl(0b0010_0000);
+        set_sprite(&mut ppu, 0, 0, 1, 0, 0);
+
+        ppu.evaluate_sprites(15);
+        assert_eq!(ppu.line_sprites.len(), 1);
+        ppu.evaluate_sprites(16);
+        assert!(ppu.line_sprites.is_empty());
+    }
+
+    #[test]
+    fn test_evaluate_sprites_limit_and_overflow() {
+        let mut ppu = test_ppu();
+        for index in 0..9 {
+            set_sprite(&mut ppu, index, 10, 1, 0, 0);
+        }
+
+        ppu.evaluate_sprites(10);
+        assert_eq!(ppu.line_sprites.len(), MAX_SPRITES_PER_LINE);
+        assert!(ppu.status.contains(StatusRegister::SPRITE_OVERFLOW));
+    }
+
+    #[test]
+    fn test_evaluate_sprites_overflow_false_positive() {
+        let mut ppu = test_ppu();
+        for index in 0..8 {
+            set_sprite(&mut ppu, index, 10, 1, 0, 0);
+        }
+        // 10番目のスプライトのタイル番号がY座標として比較される
+        set_sprite(&mut ppu, 9, 0xFF, 10, 0xFF, 0xFF);
+
+        ppu.evaluate_sprites(10);
+        assert!(ppu.status.contains(StatusRegister::SPRITE_OVERFLOW));
+    }
+
+    #[test]
+    fn test_evaluate_sprites_overflow_false_negative() {
+        let mut ppu = test_ppu();
+        for index in 0..8 {
+            set_sprite(&mut ppu, index, 10, 1, 0, 0);
+        }
+        // 10番目のスプライトはラインにあるが、Y座標ではなくタイル番号が比較される
+        set_sprite(&mut ppu, 9, 10, 0xFF, 0xFF, 0xFF);
+
+        ppu.evaluate_sprites(10);
+        assert!(!ppu.status.contains(StatusRegister::SPRITE_OVERFLOW));
+    }
+
+    fn fetch(ppu: &NesPPU, y: usize) -> [Option<SpritePixel>; WIDTH] {
+        let mut line = [None; WIDTH];
+        ppu.fetch_sprite_line(y, &mut line);
+        line
+    }
+
+    #[test]
+    fn test_fetch_sprite_line_flip() {
+        let mut ppu = test_ppu();
+        set_sprite(&mut ppu, 0, 9, 1, 0, 20);
+        ppu.evaluate_sprites(9);
+        assert!(fetch(&ppu, 10)[20].is_some());
+
+        set_sprite(&mut ppu, 0, 9, 1, ATTRIBUTE_FLIP_HORIZONTAL, 20);
+        ppu.evaluate_sprites(9);
+        assert!(fetch(&ppu, 10)[20].is_none());
+        assert!(fetch(&ppu, 10)[27].is_some());
+
+        set_sprite(&mut ppu, 0, 2, 1, ATTRIBUTE_FLIP_VERTICAL, 20);
+        ppu.evaluate_sprites(9);
+        assert!(fetch(&ppu, 10)[20].is_some());
+    }
+
+    #[test]
+    fn test_fetch_sprite_line_8x16_uses_tile_bank() {
+        let mut ppu = test_ppu();
+        ppu.write_to_ctrl(0b0010_0000);
+        // タイル番号3 -> バンク$1000のタイル2,3
+        ppu.chr_rom[0x1000 + 3 * 16 + 8] = 0x80;
+        set_sprite(&mut ppu, 0, 0, 3, 0, 0);
+
+        ppu.evaluate_sprites(8);
+        let line = fetch(&ppu, 9);
+        assert_eq!(line[0].map(|sprite| sprite.pixel), Some(0b10));
+    }
+
+    #[test]
+    fn test_fetch_sprite_line_lower_index_wins() {
+        let mut ppu = test_ppu();
+        set_sprite(&mut ppu, 0, 0, 2, 0b01, 0);
+        set_sprite(&mut ppu, 1, 0, 2, 0b10, 4);
+
+        ppu.evaluate_sprites(0);
+        let line = fetch(&ppu, 1);
+        assert_eq!(line[4].map(|sprite| sprite.pixel), Some(0b0110));
+        assert_eq!(line[8].map(|sprite| sprite.pixel), Some(0b1010));
+    }
+}
@@ -49,11 +49,15 @@ impl Header {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[allow(non_camel_case_types)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Mirroring {
     VERTICAL,
     HORIZONTAL,
     FOURSCREEN,
+    // マッパーが実行中に切り替える1画面モード
+    SINGLE_SCREEN_LOWER,
+    SINGLE_SCREEN_UPPER,
 }
 
 pub struct Rom {
@@ -1,11 +1,11 @@
 use crate::cpu::{AddressingMode, Mem, CPU};
 use crate::opcodes::{self, OpCode, OpGroup, Operation};
 
-pub fn trace(cpu: &CPU) -> String {
+pub fn trace<B: Mem>(cpu: &CPU<B>) -> String {
     let formatted_program_counter = format!("{:04X}", cpu.program_counter);
 
     let opcodes_map = &opcodes::OPCODES_MAP;
-    let current_code = cpu.mem_read(cpu.program_counter);
+    let current_code = cpu.mem_peek(cpu.program_counter);
     let current_opcode = opcodes_map.get(&current_code).expect(
         format!(
             "Invalid opcode pc: {:#X} code: {:#X} ",
@@ -40,12 +40,12 @@ pub fn trace(cpu: &CPU) -> String {
     )
 }
 
-fn fetch_instruction_bytes(cpu: &CPU, opcode: OpCode, program_counter: u16) -> Vec<u8> {
+fn fetch_instruction_bytes<B: Mem>(cpu: &CPU<B>, opcode: OpCode, program_counter: u16) -> Vec<u8> {
     let mut instruction_bytes: Vec<u8> = Vec::new();
 
     // instrcutionの先頭の1byteを除いた長さ
     for i in 0..=(opcode.len - 1) {
-        let byte = cpu.mem_read(program_counter + i as u16);
+        let byte = cpu.mem_peek(program_counter + i as u16);
         instruction_bytes.push(byte)
     }
 
@@ -66,19 +66,24 @@ fn format_instruction(instruction_bytes: Vec<u8>) -> String {
         .collect::<String>()
 }
 
-fn format_register(cpu: &CPU) -> String {
+fn format_register<B: Mem>(cpu: &CPU<B>) -> String {
+    let (scanline, dot) = cpu.bus.ppu_position();
+
     String::from(format!(
-        "A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X}",
+        "A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:>3},{:>3} CYC:{}",
         cpu.accumulator,
         cpu.index_register_x,
         cpu.index_register_y,
         cpu.status.bits(),
         cpu.stack_pointer,
+        scanline,
+        dot,
+        cpu.cycles,
     ))
 }
 
-fn format_asm_opcode(
-    cpu: &CPU,
+fn format_asm_opcode<B: Mem>(
+    cpu: &CPU<B>,
     program_counter: u16,
     mode: AddressingMode,
     mnemonic: Operation,
@@ -93,7 +98,7 @@ fn format_asm_opcode(
         AddressingMode::Absolute_X => format_absolute_x_mode_asm(cpu, program_counter, mnemonic),
         AddressingMode::Absolute_Y => format_absolute_y_mode_asm(cpu, program_counter, mnemonic),
         AddressingMode::Indirect => {
-            format_indirect_mode_asm(cpu, program_counter, mnemonic, operand_bytes, mode)
+            format_indirect_mode_asm(cpu, program_counter, mnemonic, operand_bytes)
         }
         AddressingMode::Indirect_X => format_indirect_x_mode_asm(cpu, mnemonic, operand_bytes),
         AddressingMode::Indirect_Y => format_indirect_y_mode_asm(cpu, mnemonic, operand_bytes),
@@ -104,24 +109,28 @@ fn format_asm_opcode(
     }
 }
 
-fn format_imm_mode_asm(cpu: &CPU, program_counter: u16, mnemonic: Operation) -> String {
-    let memory_value = cpu.mem_read(program_counter);
+fn format_imm_mode_asm<B: Mem>(cpu: &CPU<B>, program_counter: u16, mnemonic: Operation) -> String {
+    let memory_value = cpu.mem_peek(program_counter);
     format!("{:03?} #${:02X}", mnemonic, memory_value)
 }
 
-fn format_zero_mode_asm(cpu: &CPU, program_counter: u16, mnemonic: Operation) -> String {
-    let target_addr = cpu.mem_read(program_counter) as u16;
-    let memory_value = cpu.mem_read(target_addr);
+fn format_zero_mode_asm<B: Mem>(cpu: &CPU<B>, program_counter: u16, mnemonic: Operation) -> String {
+    let target_addr = cpu.mem_peek(program_counter) as u16;
+    let memory_value = cpu.mem_peek(target_addr);
     format!(
         "{:03?} ${:02X} = {:02X}",
         mnemonic, target_addr, memory_value
     )
 }
 
-fn format_zero_x_mode_asm(cpu: &CPU, program_counter: u16, mnemonic: Operation) -> String {
-    let addr = cpu.mem_read(program_counter);
+fn format_zero_x_mode_asm<B: Mem>(
+    cpu: &CPU<B>,
+    program_counter: u16,
+    mnemonic: Operation,
+) -> String {
+    let addr = cpu.mem_peek(program_counter);
     let target_addr = addr.wrapping_add(cpu.index_register_x) as u16;
-    let memory_value = cpu.mem_read(target_addr);
+    let memory_value = cpu.mem_peek(target_addr);
 
     format!(
         "{:03?} ${:02X},X @ {:02X} = {:02X}",
@@ -129,10 +138,14 @@ fn format_zero_x_mode_asm(cpu: &CPU, program_counter: u16, mnemonic: Operation)
     )
 }
 
-fn format_zero_y_mode_asm(cpu: &CPU, program_counter: u16, mnemonic: Operation) -> String {
-    let addr = cpu.mem_read(program_counter);
+fn format_zero_y_mode_asm<B: Mem>(
+    cpu: &CPU<B>,
+    program_counter: u16,
+    mnemonic: Operation,
+) -> String {
+    let addr = cpu.mem_peek(program_counter);
     let target_addr = addr.wrapping_add(cpu.index_register_y) as u16;
-    let memory_value = cpu.mem_read(target_addr);
+    let memory_value = cpu.mem_peek(target_addr);
 
     format!(
         "{:03?} ${:02X},Y @ {:02X} = {:02X}",
@@ -140,15 +153,19 @@ fn format_zero_y_mode_asm(cpu: &CPU, program_counter: u16, mnemonic: Operation)
     )
 }
 
-fn format_absolute_mode_asm(cpu: &CPU, program_counter: u16, mnemonic: Operation) -> String {
-    let target_addr = cpu.mem_read_u16(program_counter);
+fn format_absolute_mode_asm<B: Mem>(
+    cpu: &CPU<B>,
+    program_counter: u16,
+    mnemonic: Operation,
+) -> String {
+    let target_addr = cpu.mem_peek_u16(program_counter);
 
     match mnemonic {
         Operation::JMP | Operation::JSR => {
             format!("{:03?} ${:04X}", mnemonic, target_addr)
         }
         _ => {
-            let memory_value = cpu.mem_read(target_addr);
+            let memory_value = cpu.mem_peek(target_addr);
             format!(
                 "{:03?} ${:04X} = {:02X}",
                 mnemonic, target_addr, memory_value
@@ -157,10 +174,14 @@ fn format_absolute_mode_asm(cpu: &CPU, program_counter: u16, mnemonic: Operation
     }
 }
 
-fn format_absolute_x_mode_asm(cpu: &CPU, program_counter: u16, mnemonic: Operation) -> String {
-    let addr = cpu.mem_read_u16(program_counter);
+fn format_absolute_x_mode_asm<B: Mem>(
+    cpu: &CPU<B>,
+    program_counter: u16,
+    mnemonic: Operation,
+) -> String {
+    let addr = cpu.mem_peek_u16(program_counter);
     let target_addr = addr.wrapping_add(cpu.index_register_x as u16);
-    let memory_value = cpu.mem_read(target_addr);
+    let memory_value = cpu.mem_peek(target_addr);
 
     format!(
         "{:03?} ${:04X},X @ {:04X} = {:02X}",
@@ -168,10 +189,14 @@ fn format_absolute_x_mode_asm(cpu: &CPU, program_counter: u16, mnemonic: Operati
     )
 }
 
-fn format_absolute_y_mode_asm(cpu: &CPU, program_counter: u16, mnemonic: Operation) -> String {
-    let addr = cpu.mem_read_u16(program_counter);
+fn format_absolute_y_mode_asm<B: Mem>(
+    cpu: &CPU<B>,
+    program_counter: u16,
+    mnemonic: Operation,
+) -> String {
+    let addr = cpu.mem_peek_u16(program_counter);
     let target_addr = addr.wrapping_add(cpu.index_register_y as u16);
-    let memory_value = cpu.mem_read(target_addr);
+    let memory_value = cpu.mem_peek(target_addr);
 
     format!(
         "{:03?} ${:04X},Y @ {:04X} = {:02X}",
@@ -182,23 +207,22 @@ fn format_absolute_y_mode_asm(cpu: &CPU, program_counter: u16, mnemonic: Operati
 const LOW_PAGE_END: u16 = 0x00FF;
 const HIGH_PAGE_START: u16 = 0xFF00;
 
-fn format_indirect_mode_asm(
-    cpu: &CPU,
+fn format_indirect_mode_asm<B: Mem>(
+    cpu: &CPU<B>,
     program_counter: u16,
     mnemonic: Operation,
     operand_bytes: Vec<u8>,
-    mode: AddressingMode,
 ) -> String {
     match mnemonic {
         Operation::JMP => {
-            let addr = cpu.mem_read_u16(program_counter);
+            let addr = cpu.mem_peek_u16(program_counter);
 
             let target_addr = if addr & LOW_PAGE_END == LOW_PAGE_END {
-                let lo = cpu.mem_read(addr);
-                let hi = cpu.mem_read(addr & HIGH_PAGE_START);
+                let lo = cpu.mem_peek(addr);
+                let hi = cpu.mem_peek(addr & HIGH_PAGE_START);
                 (hi as u16) << 8 | (lo as u16)
             } else {
-                cpu.mem_read_u16(addr)
+                cpu.mem_peek_u16(addr)
             };
 
             format!("{:03?} (${:04X}) = {:04X}", mnemonic, addr, target_addr)
@@ -208,18 +232,22 @@ fn format_indirect_mode_asm(
                 "{:03?} (${:04X}) = {:02X}",
                 mnemonic,
                 operand_bytes[0],
-                cpu.mem_read(cpu.get_operand_address(&mode))
+                cpu.mem_peek(cpu.mem_peek_u16(program_counter))
             )
         }
     }
 }
 
-fn format_indirect_x_mode_asm(cpu: &CPU, mnemonic: Operation, operand_bytes: Vec<u8>) -> String {
+fn format_indirect_x_mode_asm<B: Mem>(
+    cpu: &CPU<B>,
+    mnemonic: Operation,
+    operand_bytes: Vec<u8>,
+) -> String {
     let addr = (operand_bytes[0] as u8).wrapping_add(cpu.index_register_x);
-    let lo = cpu.mem_read(addr as u16);
-    let hi = cpu.mem_read(addr.wrapping_add(1) as u16);
+    let lo = cpu.mem_peek(addr as u16);
+    let hi = cpu.mem_peek(addr.wrapping_add(1) as u16);
     let target_addr = (hi as u16) << 8 | (lo as u16);
-    let memory_value = cpu.mem_read(target_addr);
+    let memory_value = cpu.mem_peek(target_addr);
 
     format!(
         "{:03?} (${:02X},X) @ {:02X} = {:04X} = {:02X}",
@@ -227,13 +255,17 @@ fn format_indirect_x_mode_asm(cpu: &CPU, mnemonic: Operation, operand_bytes: Vec
     )
 }
 
-fn format_indirect_y_mode_asm(cpu: &CPU, mnemonic: Operation, operand_bytes: Vec<u8>) -> String {
+fn format_indirect_y_mode_asm<B: Mem>(
+    cpu: &CPU<B>,
+    mnemonic: Operation,
+    operand_bytes: Vec<u8>,
+) -> String {
     let base = operand_bytes[0];
-    let lo = cpu.mem_read(base as u16);
-    let hi = cpu.mem_read((base).wrapping_add(1) as u16);
+    let lo = cpu.mem_peek(base as u16);
+    let hi = cpu.mem_peek((base).wrapping_add(1) as u16);
     let deref_base = (hi as u16) << 8 | (lo as u16);
     let deref_addr = deref_base.wrapping_add(cpu.index_register_y as u16);
-    let memory_value = cpu.mem_read(deref_addr);
+    let memory_value = cpu.mem_peek(deref_addr);
 
     format!(
         "{:03?} (${:02X}),Y = {:04X} @ {:04X} = {:02X}",
@@ -241,10 +273,111 @@ fn format_indirect_y_mode_asm(cpu: &CPU, mnemonic: Operation, operand_bytes: Vec
     )
 }
 
-fn format_relative_mode_asm(cpu: &CPU, mnemonic: Operation, program_counter: u16) -> String {
-    let base = cpu.mem_read(program_counter) as i8;
+fn format_relative_mode_asm<B: Mem>(
+    cpu: &CPU<B>,
+    mnemonic: Operation,
+    program_counter: u16,
+) -> String {
+    let base = cpu.mem_peek(program_counter) as i8;
     let addr = (program_counter as i16).wrapping_add(base as i16) as u16;
     let target_addr = addr + 1;
 
     format!("{:03?} ${:04X}", mnemonic, target_addr)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::{Bus, FlatBus};
+    use crate::rom::test;
+
+    #[test]
+    fn test_format_trace() {
+        let mut bus = FlatBus::new();
+        bus.mem_write(100, 0xA2);
+        bus.mem_write(101, 0x01);
+        bus.mem_write(102, 0xCA);
+        bus.mem_write(103, 0x88);
+        bus.mem_write(104, 0x00);
+
+        let mut cpu = CPU::new(bus);
+        cpu.program_counter = 0x64;
+        cpu.accumulator = 1;
+        cpu.index_register_x = 2;
+        cpu.index_register_y = 3;
+
+        let mut result: Vec<String> = vec![];
+        cpu.run_with_callback(|cpu| {
+            result.push(trace(cpu));
+            if result.len() == 3 {
+                cpu.halted = true;
+            }
+        });
+
+        assert_eq!(
+            "0064  A2 01     LDX #$01                        A:01 X:02 Y:03 P:00 SP:FD PPU:  0,  0 CYC:0",
+            result[0]
+        );
+        assert_eq!(
+            "0066  CA        DEX                             A:01 X:01 Y:03 P:00 SP:FD PPU:  0,  0 CYC:2",
+            result[1]
+        );
+        assert_eq!(
+            "0067  88        DEY                             A:01 X:00 Y:03 P:02 SP:FD PPU:  0,  0 CYC:4",
+            result[2]
+        );
+    }
+
+    #[test]
+    fn test_format_mem_access() {
+        let mut bus = FlatBus::new();
+        // ORA ($33), Y
+        bus.mem_write(100, 0x11);
+        bus.mem_write(101, 0x33);
+
+        // data
+        bus.mem_write(0x33, 0x00);
+        bus.mem_write(0x34, 0x04);
+
+        // target cell
+        bus.mem_write(0x400, 0xAA);
+
+        let mut cpu = CPU::new(bus);
+        cpu.program_counter = 0x64;
+        cpu.index_register_y = 0;
+
+        assert_eq!(
+            "0064  11 33     ORA ($33),Y = 0400 @ 0400 = AA  A:00 X:00 Y:00 P:00 SP:FD PPU:  0,  0 CYC:0",
+            trace(&cpu)
+        );
+    }
+
+    #[test]
+    fn test_format_ppu_position_from_bus() {
+        let mut program = vec![0xEA; 0x8000];
+        // リセットベクタ: $8000
+        program[0x7FFC] = 0x00;
+        program[0x7FFD] = 0x80;
+        let bus = Bus::new(test::test_rom(program));
+        let mut cpu = CPU::new(bus);
+        cpu.reset();
+
+        let mut result: Vec<String> = vec![];
+        cpu.run_with_callback(|cpu| {
+            result.push(trace(cpu));
+            if result.len() == 2 {
+                cpu.halted = true;
+            }
+        });
+
+        // リセットの7サイクル分 (21ドット) PPUが進んでいる
+        assert_eq!(
+            "8000  EA        NOP                             A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7",
+            result[0]
+        );
+        assert_eq!(
+            "8001  EA        NOP                             A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 27 CYC:9",
+            result[1]
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use famicom_emulator::bus::FlatBus;
+use famicom_emulator::cpu::{Mem, CPU};
+
+use std::fs;
+
+// Klaus Dormann の 6502_functional_test.bin (デフォルト設定でアセンブルしたもの)
+const FUNCTIONAL_TEST_BIN: &str = "roms/6502_functional_test.bin";
+const START_ADDR: u16 = 0x0400;
+const SUCCESS_ADDR: u16 = 0x3469;
+// 現在実行中のテスト番号が書き込まれるアドレス
+const TEST_CASE_ADDR: u16 = 0x0200;
+// 暴走した場合に打ち切る命令数 (正常終了まではおよそ3000万命令)
+const MAX_INSTRUCTIONS: usize = 100_000_000;
+
+struct TrapInfo {
+    program_counter: u16,
+    test_case: u8,
+    instructions: usize,
+}
+
+// 自分自身へのジャンプ/分岐(トラップ)に入るまで実行する
+fn run_until_trap(cpu: &mut CPU<FlatBus>) -> TrapInfo {
+    let mut prev_program_counter = None;
+    let mut instructions = 0;
+
+    cpu.run_with_callback(|cpu| {
+        if prev_program_counter == Some(cpu.program_counter) || instructions >= MAX_INSTRUCTIONS {
+            cpu.halted = true;
+            return;
+        }
+        prev_program_counter = Some(cpu.program_counter);
+        instructions += 1;
+    });
+
+    TrapInfo {
+        program_counter: cpu.program_counter,
+        test_case: cpu.mem_read(TEST_CASE_ADDR),
+        instructions,
+    }
+}
+
+// テストバイナリは同梱していないため、配置した上で `cargo test -- --ignored` で実行する
+#[test]
+#[ignore = "requires roms/6502_functional_test.bin"]
+fn test_6502_functional_test() {
+    let image = fs::read(FUNCTIONAL_TEST_BIN)
+        .unwrap_or_else(|e| panic!("failed to read {}: {}", FUNCTIONAL_TEST_BIN, e));
+    assert_eq!(
+        image.len(),
+        0x10000,
+        "functional test binary must be 64 KiB"
+    );
+
+    let mut bus = FlatBus::new();
+    bus.memory.copy_from_slice(&image);
+
+    let mut cpu = CPU::new(bus);
+    // テストにはBCD演算のテストも含まれる
+    cpu.decimal_mode = true;
+    cpu.program_counter = START_ADDR;
+
+    let trap = run_until_trap(&mut cpu);
+
+    assert!(
+        trap.instructions < MAX_INSTRUCTIONS,
+        "did not trap within {} instructions (pc: {:#06X}, test case: {:#04X})",
+        MAX_INSTRUCTIONS,
+        trap.program_counter,
+        trap.test_case
+    );
+    assert_eq!(
+        trap.program_counter, SUCCESS_ADDR,
+        "trapped at {:#06X} in test case {:#04X} after {} instructions",
+        trap.program_counter, trap.test_case, trap.instructions
+    );
+}
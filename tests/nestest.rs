@@ -0,0 +1,94 @@
+use famicom_emulator::bus::Bus;
+use famicom_emulator::cpu::CPU;
+use famicom_emulator::rom::Rom;
+use famicom_emulator::trace::trace;
+
+use std::fs;
+
+const NESTEST_ROM: &str = "roms/nestest.nes";
+const NESTEST_LOG: &str = "roms/nestest.log";
+// オートメーションモードの開始アドレス
+const AUTOMATION_START_ADDR: u16 = 0xC000;
+// 不一致の前に表示する行数
+const CONTEXT_LINES: usize = 5;
+
+struct Divergence {
+    line: usize,
+    expected: String,
+    actual: String,
+}
+
+fn format_divergence(expected_log: &[&str], divergence: &Divergence) -> String {
+    let mut message = format!(
+        "nestest diverged from {} at line {}:\n",
+        NESTEST_LOG,
+        divergence.line + 1
+    );
+
+    let context_start = divergence.line.saturating_sub(CONTEXT_LINES);
+    for line in &expected_log[context_start..divergence.line] {
+        message += &format!("  {}\n", line);
+    }
+
+    message += &format!("- {}\n", divergence.expected);
+    message += &format!("+ {}\n", divergence.actual);
+
+    let column = divergence
+        .expected
+        .chars()
+        .zip(divergence.actual.chars())
+        .take_while(|(expected, actual)| expected == actual)
+        .count();
+    message += &format!("  {}^ column {}\n", " ".repeat(column), column + 1);
+
+    message
+}
+
+// ROMとログは同梱していないため、配置した上で `cargo test -- --ignored` で実行する
+#[test]
+#[ignore = "requires roms/nestest.nes and roms/nestest.log"]
+fn test_nestest_log() {
+    let game =
+        fs::read(NESTEST_ROM).unwrap_or_else(|e| panic!("failed to read {}: {}", NESTEST_ROM, e));
+    let rom = Rom::new(&game).unwrap();
+    let log = fs::read_to_string(NESTEST_LOG)
+        .unwrap_or_else(|e| panic!("failed to read {}: {}", NESTEST_LOG, e));
+    let expected_log: Vec<&str> = log.lines().map(|line| line.trim_end()).collect();
+
+    let bus = Bus::new(rom);
+    let mut cpu = CPU::new(bus);
+    cpu.reset();
+    cpu.program_counter = AUTOMATION_START_ADDR;
+
+    let mut line = 0;
+
+    cpu.run_with_callback(|cpu| {
+        if line == expected_log.len() {
+            cpu.halted = true;
+            return;
+        }
+
+        let actual = trace(cpu);
+        let expected = expected_log[line];
+        if actual != expected {
+            let divergence = Divergence {
+                line,
+                expected: expected.to_string(),
+                actual,
+            };
+            panic!("{}", format_divergence(&expected_log, &divergence));
+        }
+
+        line += 1;
+    });
+
+    // ログの途中でCPUが停止した(JAMなど)場合も失敗にする
+    assert_eq!(
+        line,
+        expected_log.len(),
+        "CPU halted at {:#06X} after line {} of {}",
+        cpu.program_counter,
+        line,
+        expected_log.len()
+    );
+}